@@ -0,0 +1,155 @@
+use crate::crypto::sign_ed25519::PublicKey;
+use crate::crypto::{sha3_256, AnyPublicKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryInto;
+use std::str::FromStr;
+
+pub const ADDRESS_LEN: usize = 32;
+
+/// A-Block address, the SHA3-256 hash of a public key.
+/// Displayed, parsed and serialized as lowercase hex.
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct Address([u8; ADDRESS_LEN]);
+
+impl Address {
+    /// Derives the address of a public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key` - Public key to derive the address from
+    pub fn from_public_key(public_key: &PublicKey) -> Self {
+        Self::from_any_public_key(&AnyPublicKey::Ed25519(*public_key))
+    }
+
+    /// Derives the address of a public key of any supported scheme, from its raw bytes
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key` - Algorithm tagged public key to derive the address from
+    pub fn from_any_public_key(public_key: &AnyPublicKey) -> Self {
+        let mut address = [0; ADDRESS_LEN];
+        address.copy_from_slice(&sha3_256::digest(public_key.as_ref()));
+        Self(address)
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Option<Self> {
+        Some(Self(slice.try_into().ok()?))
+    }
+
+    /// Whether this address is derived from the given public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key` - Public key to check against
+    pub fn is_derived_from(&self, public_key: &PublicKey) -> bool {
+        *self == Self::from_public_key(public_key)
+    }
+}
+
+impl From<&PublicKey> for Address {
+    fn from(public_key: &PublicKey) -> Self {
+        Self::from_public_key(public_key)
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// Address parsing error types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    InvalidHex,
+    InvalidLength(usize),
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AddressError::InvalidHex => write!(f, "Address is not valid hex"),
+            AddressError::InvalidLength(len) => write!(
+                f,
+                "Address must be {ADDRESS_LEN} bytes long, found {len} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s).map_err(|_| AddressError::InvalidHex)?;
+        Self::from_slice(&bytes).ok_or(AddressError::InvalidLength(bytes.len()))
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sign_ed25519;
+
+    #[test]
+    fn should_derive_and_round_trip_address() {
+        //
+        // Arrange
+        //
+        let (public_key, _) = sign_ed25519::gen_keypair();
+        let (other_public_key, _) = sign_ed25519::gen_keypair();
+
+        //
+        // Act
+        //
+        let address = Address::from_public_key(&public_key);
+        let parsed: Address = address.to_string().parse().unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        let deserialized: Address = serde_json::from_str(&json).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(parsed, address);
+        assert_eq!(deserialized, address);
+        assert_eq!(json, format!("\"{address}\""));
+        assert!(address.is_derived_from(&public_key));
+        assert!(!address.is_derived_from(&other_public_key));
+    }
+
+    #[test]
+    fn should_reject_invalid_address_strings() {
+        //
+        // Act
+        //
+        let invalid_hex = "not an address".parse::<Address>();
+        let invalid_length = "a1c03c87".parse::<Address>();
+
+        //
+        // Assert
+        //
+        assert_eq!(invalid_hex, Err(AddressError::InvalidHex));
+        assert_eq!(invalid_length, Err(AddressError::InvalidLength(4)));
+    }
+}
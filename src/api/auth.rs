@@ -0,0 +1,1157 @@
+use crate::address::Address;
+use crate::api::errors::ApiErrorType;
+use crate::crypto::sign_ed25519::{self, SecretKey};
+use crate::crypto::webauthn::{self, Assertion};
+use crate::crypto::{sha3_256, verify_p256, AnyPublicKey, SignatureAlgorithm};
+use crate::utils::validate_signature_with_algorithm;
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+use warp::filters::path::FullPath;
+use warp::http::Method;
+use warp::hyper::body::{Buf, Bytes};
+use warp::hyper::HeaderMap;
+use warp::{Filter, Rejection};
+
+/// Default freshness window for signed requests, in seconds
+pub const DEFAULT_REPLAY_WINDOW_SECS: u64 = 300;
+
+/// Default limit on signed request bodies, in bytes
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Longest nonce accepted from a client, in bytes
+pub const MAX_NONCE_LEN: usize = 64;
+
+/// Storage for nonces that have already been used
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Records the signer's nonce until `expires_at` (UNIX seconds).
+    /// Returns `false` if the signer has already used the nonce.
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key` - Lowercase hex public key of the signer
+    /// * `nonce` - Nonce sent by the client
+    /// * `expires_at` - Time after which the nonce no longer needs to be remembered
+    async fn insert_if_new(&self, public_key: &str, nonce: &str, expires_at: u64) -> bool;
+}
+
+/// Default maximum number of unexpired nonces kept per signer by `InMemoryNonceStore`
+pub const DEFAULT_MAX_NONCES_PER_KEY: usize = 10_000;
+
+/// In-memory nonce store, pruning expired nonces as new ones come in.
+///
+/// Holds at most `max_nonces_per_key` unexpired nonces for each signer. Once a signer
+/// is at the limit, its new nonces are refused (the request is rejected) until old ones
+/// expire, as forgetting a nonce early would allow it to be replayed. Other signers
+/// are not affected.
+#[derive(Debug)]
+pub struct InMemoryNonceStore {
+    max_nonces_per_key: usize,
+    seen: Mutex<SeenNonces>,
+}
+
+/// Used nonces, indexed by signer and by expiry so pruning only visits expired entries
+#[derive(Debug, Default)]
+struct SeenNonces {
+    by_key: HashMap<String, HashSet<String>>,
+    by_expiry: BTreeSet<(u64, String, String)>,
+}
+
+impl SeenNonces {
+    fn prune(&mut self, now: u64) {
+        while let Some((expiry, _, _)) = self.by_expiry.first() {
+            if *expiry >= now {
+                break;
+            }
+            let Some((_, public_key, nonce)) = self.by_expiry.pop_first() else {
+                break;
+            };
+            if let Some(nonces) = self.by_key.get_mut(&public_key) {
+                nonces.remove(&nonce);
+                if nonces.is_empty() {
+                    self.by_key.remove(&public_key);
+                }
+            }
+        }
+    }
+}
+
+impl InMemoryNonceStore {
+    pub fn new() -> Self {
+        Self::with_max_nonces_per_key(DEFAULT_MAX_NONCES_PER_KEY)
+    }
+
+    /// Store holding at most `max_nonces_per_key` unexpired nonces for each signer
+    ///
+    /// ### Arguments
+    ///
+    /// * `max_nonces_per_key` - Maximum number of nonces to remember per signer
+    pub fn with_max_nonces_per_key(max_nonces_per_key: usize) -> Self {
+        Self {
+            max_nonces_per_key,
+            seen: Mutex::new(SeenNonces::default()),
+        }
+    }
+}
+
+impl Default for InMemoryNonceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NonceStore for InMemoryNonceStore {
+    async fn insert_if_new(&self, public_key: &str, nonce: &str, expires_at: u64) -> bool {
+        let mut seen = self.seen.lock().await;
+        seen.prune(unix_timestamp());
+
+        let nonces = seen.by_key.entry(public_key.to_string()).or_default();
+        if nonces.contains(nonce) {
+            return false;
+        }
+        if nonces.len() >= self.max_nonces_per_key {
+            warn!("Nonce limit reached for signer, refusing new nonce");
+            return false;
+        }
+
+        nonces.insert(nonce.to_string());
+        seen.by_expiry
+            .insert((expires_at, public_key.to_string(), nonce.to_string()));
+        true
+    }
+}
+
+/// Replay protection settings: the client signs `<address>:<timestamp>:<nonce>`
+/// and sends the `timestamp` and `nonce` headers along with the signature
+#[derive(Clone)]
+pub struct ReplayProtection {
+    pub window_secs: u64,
+    pub store: Arc<dyn NonceStore>,
+}
+
+impl ReplayProtection {
+    /// Replay protection with an in-memory nonce store
+    ///
+    /// ### Arguments
+    ///
+    /// * `window_secs` - Maximum distance between the request timestamp and now
+    pub fn new(window_secs: u64) -> Self {
+        Self::with_store(window_secs, Arc::new(InMemoryNonceStore::new()))
+    }
+
+    /// Replay protection with a custom nonce store, e.g. one shared between nodes
+    ///
+    /// ### Arguments
+    ///
+    /// * `window_secs` - Maximum distance between the request timestamp and now
+    /// * `store` - Store for used nonces
+    pub fn with_store(window_secs: u64, store: Arc<dyn NonceStore>) -> Self {
+        Self { window_secs, store }
+    }
+}
+
+impl Default for ReplayProtection {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_WINDOW_SECS)
+    }
+}
+
+impl std::fmt::Debug for ReplayProtection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReplayProtection")
+            .field("window_secs", &self.window_secs)
+            .finish()
+    }
+}
+
+/// Signature verification settings
+#[derive(Clone, Debug)]
+pub struct SigVerifyConfig {
+    pub replay_protection: Option<ReplayProtection>,
+    /// Reject requests whose `address` header isn't derived from the `public_key` header
+    pub enforce_address_derivation: bool,
+    /// Largest body `request_sig_verify_middleware` buffers before checking the signature
+    pub max_body_bytes: u64,
+    /// Relying party ID WebAuthn assertions must be scoped to, e.g. `app.a-block.io`.
+    /// Assertions for any relying party are accepted when `None`
+    pub webauthn_rp_id: Option<String>,
+}
+
+impl Default for SigVerifyConfig {
+    fn default() -> Self {
+        Self {
+            replay_protection: None,
+            enforce_address_derivation: false,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            webauthn_rp_id: None,
+        }
+    }
+}
+
+impl SigVerifyConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_body_bytes(mut self, max_body_bytes: u64) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    pub fn with_replay_protection(mut self, replay_protection: ReplayProtection) -> Self {
+        self.replay_protection = Some(replay_protection);
+        self
+    }
+
+    pub fn with_address_derivation_check(mut self) -> Self {
+        self.enforce_address_derivation = true;
+        self
+    }
+
+    pub fn with_webauthn_rp_id(mut self, rp_id: &str) -> Self {
+        self.webauthn_rp_id = Some(rp_id.to_string());
+        self
+    }
+}
+
+/// Message the client signs when replay protection is enabled
+///
+/// ### Arguments
+///
+/// * `address` - Address of the caller
+/// * `timestamp` - Request time in UNIX seconds
+/// * `nonce` - One-time value chosen by the client
+pub fn replay_message(address: &str, timestamp: u64, nonce: &str) -> String {
+    format!("{address}:{timestamp}:{nonce}")
+}
+
+/// Current time in UNIX seconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|n| n.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Reads the signature algorithm header, Ed25519 unless the client states otherwise,
+/// e.g. "ecdsa-p256" for P-256 signers and passkeys
+fn header_algorithm(headers: &HeaderMap) -> Result<SignatureAlgorithm, Rejection> {
+    let algorithm = match headers.get("algorithm") {
+        None => SignatureAlgorithm::default(),
+        Some(value) => match value.to_str().ok().and_then(|a| a.parse().ok()) {
+            Some(algorithm) => algorithm,
+            None => {
+                warn!("Unsupported signature algorithm");
+                return Err(warp::reject::custom(ApiErrorType::InvalidSignature));
+            }
+        },
+    };
+    debug!("algorithm: {:?}", algorithm);
+
+    Ok(algorithm)
+}
+
+/// Reads the timestamp header and checks it is within `window_secs` of now
+fn header_timestamp(headers: &HeaderMap, window_secs: u64) -> Result<u64, Rejection> {
+    match header_str(headers, "timestamp").parse::<u64>() {
+        Ok(timestamp) if unix_timestamp().abs_diff(timestamp) <= window_secs => Ok(timestamp),
+        _ => {
+            warn!("Missing or expired request timestamp");
+            Err(warp::reject::custom(ApiErrorType::RequestExpired))
+        }
+    }
+}
+
+/// Reads the nonce header, which must be present when replay protection is enabled
+/// and at most `MAX_NONCE_LEN` bytes long
+fn header_nonce(headers: &HeaderMap) -> Result<&str, Rejection> {
+    match header_str(headers, "nonce") {
+        "" => {
+            warn!("Missing request nonce");
+            Err(warp::reject::custom(ApiErrorType::NonceReplayed))
+        }
+        nonce if nonce.len() > MAX_NONCE_LEN => {
+            warn!("Request nonce over {MAX_NONCE_LEN} bytes");
+            Err(warp::reject::custom(ApiErrorType::InvalidHeader(
+                "nonce".to_string(),
+            )))
+        }
+        nonce => Ok(nonce),
+    }
+}
+
+/// Records the nonce of a valid request, rejecting it if the signer has used it before.
+/// Only valid requests get here, so others can't burn a signer's nonces.
+async fn record_nonce(
+    replay_protection: &ReplayProtection,
+    public_key: &str,
+    nonce: &str,
+    timestamp: u64,
+) -> Result<(), Rejection> {
+    // Hex decoding ignores case, so normalise it or a replay could pass as another key
+    let public_key = public_key.to_ascii_lowercase();
+    let expires_at = timestamp + replay_protection.window_secs;
+    if !replay_protection
+        .store
+        .insert_if_new(&public_key, nonce, expires_at)
+        .await
+    {
+        warn!("Replayed request nonce");
+        return Err(warp::reject::custom(ApiErrorType::NonceReplayed));
+    }
+    Ok(())
+}
+
+/// Checks that the address is the A-Block address derived from the public key
+fn check_address(
+    algorithm: SignatureAlgorithm,
+    public_key: &str,
+    address: &str,
+) -> Result<(), Rejection> {
+    let public_key = hex::decode(public_key)
+        .ok()
+        .and_then(|pk| AnyPublicKey::from_slice(algorithm, &pk));
+    let address = address.parse::<Address>().ok();
+
+    match (public_key, address) {
+        (Some(public_key), Some(address))
+            if address == Address::from_any_public_key(&public_key) =>
+        {
+            Ok(())
+        }
+        _ => {
+            warn!("Address is not derived from the public key");
+            Err(warp::reject::custom(ApiErrorType::AddressMismatch))
+        }
+    }
+}
+
+/// Checks the signature over the message. Clients sending the `client_data_json` and
+/// `authenticator_data` headers instead sign with a passkey, whose WebAuthn assertion
+/// must have the message as challenge.
+fn check_signature(
+    headers: &HeaderMap,
+    config: &SigVerifyConfig,
+    algorithm: SignatureAlgorithm,
+    msg: &str,
+) -> Result<(), Rejection> {
+    let public_key = header_str(headers, "public_key");
+    let signature = header_str(headers, "signature");
+
+    let is_valid = if headers.contains_key("client_data_json") {
+        check_assertion(headers, config, algorithm, msg)
+    } else {
+        validate_signature_with_algorithm(algorithm, public_key, msg, signature)
+    };
+    if is_valid {
+        debug!("Signature is valid");
+        return Ok(());
+    }
+
+    warn!("Invalid signature");
+    Err(warp::reject::custom(ApiErrorType::InvalidSignature))
+}
+
+/// Checks the WebAuthn assertion sent in the request headers, from a P-256 passkey
+fn check_assertion(
+    headers: &HeaderMap,
+    config: &SigVerifyConfig,
+    algorithm: SignatureAlgorithm,
+    msg: &str,
+) -> bool {
+    let public_key = hex::decode(header_str(headers, "public_key"))
+        .ok()
+        .and_then(|pk| verify_p256::PublicKey::from_slice(&pk));
+
+    match (algorithm, public_key, header_assertion(headers)) {
+        (SignatureAlgorithm::EcdsaP256, Some(public_key), Some(assertion)) => {
+            webauthn::verify_assertion(
+                &assertion,
+                msg.as_bytes(),
+                config.webauthn_rp_id.as_deref(),
+                &public_key,
+            )
+        }
+        _ => {
+            warn!("Failed to decode {algorithm} public key or WebAuthn assertion");
+            false
+        }
+    }
+}
+
+/// Reads a hex encoded WebAuthn assertion from the request headers
+fn header_assertion(headers: &HeaderMap) -> Option<Assertion> {
+    let signature = hex::decode(header_str(headers, "signature")).ok()?;
+    Some(Assertion {
+        authenticator_data: hex::decode(header_str(headers, "authenticator_data")).ok()?,
+        client_data_json: hex::decode(header_str(headers, "client_data_json")).ok()?,
+        signature: verify_p256::Signature::from_slice(&signature)?,
+    })
+}
+
+/// Middleware filter to handle signature verification with the given settings
+///
+/// ### Arguments
+///
+/// * `config` - Signature verification settings
+pub fn sig_verify_middleware_with_config(
+    config: SigVerifyConfig,
+) -> impl Filter<Extract = ((),), Error = Rejection> + Clone {
+    warp::header::headers_cloned().and_then(move |headers: HeaderMap| {
+        debug!("Validating signature");
+        let config = config.clone();
+
+        async move {
+            let public_key = header_str(&headers, "public_key");
+            debug!("public_key: {:?}", public_key);
+
+            let address = header_str(&headers, "address");
+            debug!("address: {:?}", address);
+
+            let signature = header_str(&headers, "signature");
+            debug!("signature: {:?}", signature);
+
+            let algorithm = header_algorithm(&headers)?;
+
+            if config.enforce_address_derivation {
+                check_address(algorithm, public_key, address)?;
+            }
+
+            let Some(replay_protection) = &config.replay_protection else {
+                // Proceed to the next filter/handler
+                return check_signature(&headers, &config, algorithm, address);
+            };
+
+            let timestamp = header_timestamp(&headers, replay_protection.window_secs)?;
+            let nonce = header_nonce(&headers)?;
+
+            let msg = replay_message(address, timestamp, nonce);
+            check_signature(&headers, &config, algorithm, &msg)?;
+            record_nonce(replay_protection, public_key, nonce, timestamp).await
+        }
+    })
+}
+
+/// Identity of a caller whose request signature has been verified
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthenticatedCaller {
+    pub public_key: AnyPublicKey,
+    pub address: Address,
+}
+
+/// Middleware filter verifying the signature like `sig_verify_middleware_with_config`,
+/// then extracting the caller's identity for the handler.
+/// The address is always checked to be derived from the public key, whatever `config` says,
+/// so handlers can trust both. Rejects requests whose `address` header can't be parsed.
+///
+/// ### Arguments
+///
+/// * `config` - Signature verification settings
+pub fn authenticated_caller(
+    config: SigVerifyConfig,
+) -> impl Filter<Extract = (AuthenticatedCaller,), Error = Rejection> + Clone {
+    caller_address()
+        .and(sig_verify_middleware_with_config(
+            config.with_address_derivation_check(),
+        ))
+        .and(warp::header::headers_cloned())
+        .and_then(|address: Address, _, headers: HeaderMap| async move {
+            authenticated_caller_from(address, &headers)
+        })
+}
+
+/// Middleware filter verifying the signature over the whole request like
+/// `request_sig_verify_middleware`, then extracting the caller's identity and the body.
+/// The address is checked as with `authenticated_caller`.
+///
+/// ### Arguments
+///
+/// * `config` - Signature verification settings
+pub fn authenticated_request_caller(
+    config: SigVerifyConfig,
+) -> impl Filter<Extract = (AuthenticatedCaller, Bytes), Error = Rejection> + Clone {
+    caller_address()
+        .and(request_sig_verify_middleware(
+            config.with_address_derivation_check(),
+        ))
+        .and(warp::header::headers_cloned())
+        .and_then(
+            |address: Address, body: Bytes, headers: HeaderMap| async move {
+                Ok::<_, Rejection>((authenticated_caller_from(address, &headers)?, body))
+            },
+        )
+        .untuple_one()
+}
+
+/// Parses the `address` header of the caller
+fn caller_address() -> impl Filter<Extract = (Address,), Error = Rejection> + Clone {
+    warp::header::headers_cloned().and_then(|headers: HeaderMap| async move {
+        header_str(&headers, "address")
+            .parse::<Address>()
+            .map_err(|_| {
+                warn!("Failed to parse address of caller");
+                warp::reject::custom(ApiErrorType::InvalidHeader("address".to_string()))
+            })
+    })
+}
+
+/// Builds the identity of a caller whose signature and address have been verified
+fn authenticated_caller_from(
+    address: Address,
+    headers: &HeaderMap,
+) -> Result<AuthenticatedCaller, Rejection> {
+    let algorithm = header_algorithm(headers)?;
+    match hex::decode(header_str(headers, "public_key"))
+        .ok()
+        .and_then(|pk| AnyPublicKey::from_slice(algorithm, &pk))
+    {
+        Some(public_key) => Ok(AuthenticatedCaller {
+            public_key,
+            address,
+        }),
+        None => {
+            warn!("Failed to parse public key of caller");
+            Err(warp::reject::custom(ApiErrorType::InvalidSignature))
+        }
+    }
+}
+
+/// Message the client signs for a request signature: the HTTP method, full path,
+/// raw query string, hex SHA3-256 hash of the body, timestamp and nonce, one per line.
+/// The nonce is empty when replay protection isn't used.
+///
+/// ### Arguments
+///
+/// * `method` - HTTP method, e.g. `POST`
+/// * `path` - Full request path
+/// * `query` - Raw query string, without the leading `?`
+/// * `body` - Request body
+/// * `timestamp` - Request time in UNIX seconds
+/// * `nonce` - One-time value chosen by the client
+pub fn request_signature_message(
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+    timestamp: u64,
+    nonce: &str,
+) -> String {
+    let body_hash = hex::encode(sha3_256::digest(body));
+    format!(
+        "{}\n{path}\n{query}\n{body_hash}\n{timestamp}\n{nonce}",
+        method.to_ascii_uppercase()
+    )
+}
+
+/// Produces the headers for a signed request, to be verified by `request_sig_verify_middleware`
+///
+/// ### Arguments
+///
+/// * `sk` - Secret key to sign with
+/// * `method` - HTTP method, e.g. `POST`
+/// * `path` - Full request path
+/// * `query` - Raw query string, without the leading `?`
+/// * `body` - Request body
+/// * `nonce` - One-time value, required when the server uses replay protection
+pub fn sign_request_headers(
+    sk: &SecretKey,
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+    nonce: Option<&str>,
+) -> Option<Vec<(&'static str, String)>> {
+    let public_key = sk.public_key()?;
+    let timestamp = unix_timestamp();
+    let msg = request_signature_message(
+        method,
+        path,
+        query,
+        body,
+        timestamp,
+        nonce.unwrap_or_default(),
+    );
+    let signature = sign_ed25519::sign_detached(msg.as_bytes(), sk);
+
+    let mut headers = vec![
+        ("public_key", public_key.to_string()),
+        ("address", Address::from_public_key(&public_key).to_string()),
+        ("signature", signature.to_string()),
+        ("timestamp", timestamp.to_string()),
+    ];
+    if let Some(nonce) = nonce {
+        headers.push(("nonce", nonce.to_string()));
+    }
+    Some(headers)
+}
+
+/// Buffers the request body, rejecting it with `PayloadTooLarge` once it exceeds `limit`.
+/// Works with and without a `content-length` header, e.g. for chunked bodies.
+///
+/// ### Arguments
+///
+/// * `limit` - Maximum body size in bytes
+fn limited_body(limit: u64) -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::header::optional::<u64>("content-length")
+        .and(warp::body::stream())
+        .and_then(move |length: Option<u64>, stream| async move {
+            if length.is_some_and(|length| length > limit) {
+                return Err(payload_too_large(limit));
+            }
+            read_limited(stream, limit).await
+        })
+}
+
+fn payload_too_large(limit: u64) -> Rejection {
+    warn!("Request body over {limit} bytes");
+    warp::reject::custom(ApiErrorType::PayloadTooLarge)
+}
+
+/// Reads a body stream into memory, stopping as soon as it exceeds `limit`
+async fn read_limited<S, B>(stream: S, limit: u64) -> Result<Bytes, Rejection>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    futures::pin_mut!(stream);
+
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let mut chunk = chunk.map_err(|e| {
+            warn!("Failed to read request body: {e}");
+            warp::reject::custom(ApiErrorType::InvalidRequestBody(e.to_string()))
+        })?;
+        if (body.len() + chunk.remaining()) as u64 > limit {
+            return Err(payload_too_large(limit));
+        }
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+
+    Ok(Bytes::from(body))
+}
+
+/// Middleware filter verifying a signature over the whole request,
+/// as produced by `sign_request_headers`. Buffers the body, up to
+/// `config.max_body_bytes`, and extracts it for the handler.
+///
+/// ### Arguments
+///
+/// * `config` - Signature verification settings. The replay protection window, or
+///   `DEFAULT_REPLAY_WINDOW_SECS` without it, bounds the request timestamp.
+pub fn request_sig_verify_middleware(
+    config: SigVerifyConfig,
+) -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    let query = warp::query::raw().or(warp::any().map(String::new)).unify();
+
+    warp::method()
+        .and(warp::path::full())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .and(limited_body(config.max_body_bytes))
+        .and_then(
+            move |method: Method,
+                  path: FullPath,
+                  query: String,
+                  headers: HeaderMap,
+                  body: Bytes| {
+                debug!("Validating request signature");
+                let config = config.clone();
+
+                async move {
+                    let public_key = header_str(&headers, "public_key");
+                    let algorithm = header_algorithm(&headers)?;
+
+                    if config.enforce_address_derivation {
+                        check_address(algorithm, public_key, header_str(&headers, "address"))?;
+                    }
+
+                    let window_secs = config
+                        .replay_protection
+                        .as_ref()
+                        .map_or(DEFAULT_REPLAY_WINDOW_SECS, |r| r.window_secs);
+                    let timestamp = header_timestamp(&headers, window_secs)?;
+                    let nonce = match &config.replay_protection {
+                        Some(_) => header_nonce(&headers)?,
+                        None => header_str(&headers, "nonce"),
+                    };
+
+                    let msg = request_signature_message(
+                        method.as_str(),
+                        path.as_str(),
+                        &query,
+                        &body,
+                        timestamp,
+                        nonce,
+                    );
+                    check_signature(&headers, &config, algorithm, &msg)?;
+
+                    if let Some(replay_protection) = &config.replay_protection {
+                        record_nonce(replay_protection, public_key, nonce, timestamp).await?;
+                    }
+
+                    Ok::<_, Rejection>(body)
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sign_ed25519;
+
+    fn signed_request(
+        (pk, sk): &(sign_ed25519::PublicKey, SecretKey),
+        timestamp: u64,
+        nonce: &str,
+    ) -> warp::test::RequestBuilder {
+        let address = "a1c03c87549f1cb6f277f24e65111736c1faa8c47d20f3cf8ebaa595c252503c";
+        let msg = replay_message(address, timestamp, nonce);
+        let signature = sign_ed25519::sign_detached(msg.as_bytes(), sk);
+
+        warp::test::request()
+            .header("public_key", pk.to_string())
+            .header("address", address)
+            .header("signature", signature.to_string())
+            .header("timestamp", timestamp.to_string())
+            .header("nonce", nonce)
+    }
+
+    fn rejection_error(rejection: Rejection) -> Option<ApiErrorType> {
+        rejection.find::<ApiErrorType>().cloned()
+    }
+
+    #[tokio::test]
+    async fn should_bound_and_prune_nonce_store() {
+        //
+        // Arrange
+        //
+        let store = InMemoryNonceStore::with_max_nonces_per_key(2);
+        let now = unix_timestamp();
+
+        //
+        // Act
+        //
+        let expired = store.insert_if_new("a", "expired", now - 10).await;
+        let first = store.insert_if_new("a", "first", now + 60).await;
+        let second = store.insert_if_new("a", "second", now + 60).await;
+        let over_limit = store.insert_if_new("a", "third", now + 60).await;
+        let replayed = store.insert_if_new("a", "first", now + 60).await;
+
+        //
+        // Assert
+        //
+        assert!(expired && first && second);
+        assert!(!over_limit);
+        assert!(!replayed);
+    }
+
+    #[tokio::test]
+    async fn should_not_block_other_keys_when_one_is_full() {
+        //
+        // Arrange
+        //
+        let store = InMemoryNonceStore::with_max_nonces_per_key(1);
+        let expires_at = unix_timestamp() + 60;
+        store.insert_if_new("attacker", "nonce-1", expires_at).await;
+
+        //
+        // Act
+        //
+        let attacker = store.insert_if_new("attacker", "nonce-2", expires_at).await;
+        let same_nonce = store.insert_if_new("honest", "nonce-1", expires_at).await;
+        let other_nonce = store.insert_if_new("other", "nonce-2", expires_at).await;
+
+        //
+        // Assert
+        //
+        assert!(!attacker);
+        assert!(same_nonce && other_nonce);
+    }
+
+    #[tokio::test]
+    async fn should_reject_replayed_nonce() {
+        //
+        // Arrange
+        //
+        let config = SigVerifyConfig::new().with_replay_protection(ReplayProtection::default());
+        let filter = sig_verify_middleware_with_config(config);
+        let keypair = sign_ed25519::gen_keypair();
+        let request = signed_request(&keypair, unix_timestamp(), "nonce-1");
+
+        //
+        // Act
+        //
+        let first = request.filter(&filter).await;
+        let replayed = signed_request(&keypair, unix_timestamp(), "nonce-1")
+            .filter(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert!(first.is_ok());
+        assert!(matches!(
+            replayed.map_err(rejection_error),
+            Err(Some(ApiErrorType::NonceReplayed))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_reject_overlong_and_case_changed_replays() {
+        //
+        // Arrange
+        //
+        let config = SigVerifyConfig::new().with_replay_protection(ReplayProtection::default());
+        let filter = sig_verify_middleware_with_config(config);
+        let (pk, sk) = sign_ed25519::gen_keypair();
+        let address = Address::from_public_key(&pk).to_string();
+        let timestamp = unix_timestamp();
+        let request = |public_key: &str, nonce: &str| {
+            let msg = replay_message(&address, timestamp, nonce);
+            warp::test::request()
+                .header("public_key", public_key)
+                .header("address", &address)
+                .header(
+                    "signature",
+                    sign_ed25519::sign_detached(msg.as_bytes(), &sk).to_string(),
+                )
+                .header("timestamp", timestamp.to_string())
+                .header("nonce", nonce)
+        };
+
+        //
+        // Act
+        //
+        let overlong = request(&pk.to_string(), &"n".repeat(MAX_NONCE_LEN + 1))
+            .filter(&filter)
+            .await;
+        let first = request(&pk.to_string(), "nonce-1").filter(&filter).await;
+        let upper_case = request(&pk.to_string().to_uppercase(), "nonce-1")
+            .filter(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            overlong.map_err(rejection_error).err(),
+            Some(Some(ApiErrorType::InvalidHeader("nonce".to_string())))
+        );
+        assert!(first.is_ok());
+        assert!(matches!(
+            upper_case.map_err(rejection_error),
+            Err(Some(ApiErrorType::NonceReplayed))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_verify_request_signature_over_body() {
+        //
+        // Arrange
+        //
+        let (_, sk) = sign_ed25519::gen_keypair();
+        let filter = request_sig_verify_middleware(SigVerifyConfig::new());
+        let body = b"{\"title\":\"Hello World!\"}";
+        let headers = sign_request_headers(&sk, "POST", "/listings", "page=1", body, None).unwrap();
+        let request = |body: &[u8]| {
+            headers
+                .iter()
+                .fold(
+                    warp::test::request()
+                        .method("POST")
+                        .path("/listings?page=1"),
+                    |request, (name, value)| request.header(*name, value),
+                )
+                .body(body)
+        };
+
+        //
+        // Act
+        //
+        let result = request(body).filter(&filter).await;
+        let tampered = request(b"{}").filter(&filter).await;
+        let other_path = request(body).path("/other?page=1").filter(&filter).await;
+        let other_query = request(body).path("/listings?page=2").filter(&filter).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(result.ok(), Some(Bytes::from_static(body)));
+        for rejected in [tampered, other_path, other_query] {
+            assert!(matches!(
+                rejected.map_err(rejection_error),
+                Err(Some(ApiErrorType::InvalidSignature))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_request_body_over_limit() {
+        //
+        // Arrange
+        //
+        let (_, sk) = sign_ed25519::gen_keypair();
+        let filter = request_sig_verify_middleware(SigVerifyConfig::new().with_max_body_bytes(8));
+        let body = b"{\"title\":\"Hello World!\"}";
+        let headers = sign_request_headers(&sk, "POST", "/listings", "", body, None).unwrap();
+
+        //
+        // Act
+        //
+        let result = headers
+            .iter()
+            .fold(
+                warp::test::request().method("POST").path("/listings"),
+                |request, (name, value)| request.header(*name, value),
+            )
+            .body(body)
+            .filter(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert!(matches!(
+            result.map_err(rejection_error),
+            Err(Some(ApiErrorType::PayloadTooLarge))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_reject_address_not_derived_from_public_key() {
+        //
+        // Arrange
+        //
+        let (pk, sk) = sign_ed25519::gen_keypair();
+        let (other_pk, _) = sign_ed25519::gen_keypair();
+        let filter = sig_verify_middleware_with_config(
+            SigVerifyConfig::new().with_address_derivation_check(),
+        );
+        let request = |address: Address| {
+            let signature = sign_ed25519::sign_detached(address.to_string().as_bytes(), &sk);
+            warp::test::request()
+                .header("public_key", pk.to_string())
+                .header("address", address.to_string())
+                .header("signature", signature.to_string())
+        };
+
+        //
+        // Act
+        //
+        let own_address = request(Address::from_public_key(&pk)).filter(&filter).await;
+        let other_address = request(Address::from_public_key(&other_pk))
+            .filter(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert!(own_address.is_ok());
+        assert!(matches!(
+            other_address.map_err(rejection_error),
+            Err(Some(ApiErrorType::AddressMismatch))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_extract_authenticated_caller() {
+        //
+        // Arrange
+        //
+        let (pk, sk) = sign_ed25519::gen_keypair();
+        let address = Address::from_public_key(&pk);
+        let signature = sign_ed25519::sign_detached(address.to_string().as_bytes(), &sk);
+        let (victim_pk, _) = sign_ed25519::gen_keypair();
+        let victim = Address::from_public_key(&victim_pk);
+        let victim_signature = sign_ed25519::sign_detached(victim.to_string().as_bytes(), &sk);
+        let filter = authenticated_caller(SigVerifyConfig::new());
+        let request = |address: &str, signature: &sign_ed25519::Signature| {
+            warp::test::request()
+                .header("public_key", pk.to_string())
+                .header("address", address)
+                .header("signature", signature.to_string())
+        };
+
+        //
+        // Act
+        //
+        let caller = request(&address.to_string(), &signature)
+            .filter(&filter)
+            .await;
+        let impersonated = request(&victim.to_string(), &victim_signature)
+            .filter(&filter)
+            .await;
+        let unparseable = request("not-an-address", &signature).filter(&filter).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            caller.ok(),
+            Some(AuthenticatedCaller {
+                public_key: AnyPublicKey::Ed25519(pk),
+                address,
+            })
+        );
+        assert!(matches!(
+            impersonated.map_err(rejection_error),
+            Err(Some(ApiErrorType::AddressMismatch))
+        ));
+        assert_eq!(
+            unparseable.map_err(rejection_error).err(),
+            Some(Some(ApiErrorType::InvalidHeader("address".to_string())))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_extract_authenticated_request_caller() {
+        //
+        // Arrange
+        //
+        let (pk, sk) = sign_ed25519::gen_keypair();
+        let (victim_pk, _) = sign_ed25519::gen_keypair();
+        let filter = authenticated_request_caller(SigVerifyConfig::new());
+        let body = b"{\"title\":\"Hello World!\"}";
+        let headers = sign_request_headers(&sk, "POST", "/listings", "", body, None).unwrap();
+        let request = |address: &str| {
+            headers
+                .iter()
+                .filter(|(name, _)| *name != "address")
+                .fold(
+                    warp::test::request().method("POST").path("/listings"),
+                    |request, (name, value)| request.header(*name, value),
+                )
+                .header("address", address)
+                .body(body)
+        };
+
+        //
+        // Act
+        //
+        let caller = request(&Address::from_public_key(&pk).to_string())
+            .filter(&filter)
+            .await;
+        let impersonated = request(&Address::from_public_key(&victim_pk).to_string())
+            .filter(&filter)
+            .await;
+        let unparseable = request("not-an-address").filter(&filter).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            caller.ok(),
+            Some((
+                AuthenticatedCaller {
+                    public_key: AnyPublicKey::Ed25519(pk),
+                    address: Address::from_public_key(&pk),
+                },
+                Bytes::from_static(body)
+            ))
+        );
+        assert!(matches!(
+            impersonated.map_err(rejection_error),
+            Err(Some(ApiErrorType::AddressMismatch))
+        ));
+        assert_eq!(
+            unparseable.map_err(rejection_error).err(),
+            Some(Some(ApiErrorType::InvalidHeader("address".to_string())))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_passkey_assertions() {
+        //
+        // Arrange
+        //
+        let passkey = webauthn::TestPasskey::new();
+        let public_key = AnyPublicKey::EcdsaP256(passkey.public_key);
+        let address = Address::from_any_public_key(&public_key);
+        let filter =
+            authenticated_caller(SigVerifyConfig::new().with_webauthn_rp_id("app.a-block.io"));
+        let request = |assertion: Assertion| {
+            warp::test::request()
+                .header("public_key", hex::encode(passkey.public_key))
+                .header("address", address.to_string())
+                .header("algorithm", "es256")
+                .header("signature", hex::encode(&assertion.signature))
+                .header(
+                    "authenticator_data",
+                    hex::encode(&assertion.authenticator_data),
+                )
+                .header("client_data_json", hex::encode(&assertion.client_data_json))
+        };
+
+        //
+        // Act
+        //
+        let caller = request(passkey.assert(address.to_string().as_bytes(), "app.a-block.io"))
+            .filter(&filter)
+            .await;
+        let other_rp = request(passkey.assert(address.to_string().as_bytes(), "evil.example"))
+            .filter(&filter)
+            .await;
+        let other_challenge = request(passkey.assert(b"other", "app.a-block.io"))
+            .filter(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            caller.ok(),
+            Some(AuthenticatedCaller {
+                public_key,
+                address
+            })
+        );
+        for rejected in [other_rp, other_challenge] {
+            assert!(matches!(
+                rejected.map_err(rejection_error),
+                Err(Some(ApiErrorType::InvalidSignature))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_expired_timestamp() {
+        //
+        // Arrange
+        //
+        let config = SigVerifyConfig::new().with_replay_protection(ReplayProtection::new(60));
+        let filter = sig_verify_middleware_with_config(config);
+        let request = signed_request(
+            &sign_ed25519::gen_keypair(),
+            unix_timestamp() - 120,
+            "nonce-1",
+        );
+
+        //
+        // Act
+        //
+        let result = request.filter(&filter).await;
+
+        //
+        // Assert
+        //
+        assert!(matches!(
+            result.map_err(rejection_error),
+            Err(Some(ApiErrorType::RequestExpired))
+        ));
+    }
+}
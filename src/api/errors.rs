@@ -5,6 +5,7 @@ use warp::hyper::StatusCode;
 pub struct ApiError {
     pub code: StatusCode,
     pub message: ApiErrorType,
+    /// Request ID sent back in the error reply, omitted when empty
     pub id: String,
     pub route: String,
 }
@@ -18,6 +19,12 @@ impl ApiError {
             route,
         }
     }
+
+    /// Sets the request ID, e.g. the one `api::utils::with_request_id` hands to the routes
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self
+    }
 }
 
 impl std::fmt::Display for ApiError {
@@ -28,7 +35,8 @@ impl std::fmt::Display for ApiError {
 
 impl warp::reject::Reject for ApiError {}
 
-/// Constructs an internal server error response for the API
+/// Constructs an internal server error response for the API, without a request ID.
+/// Use `ApiError::with_id` to attach one.
 ///
 /// ### Arguments
 ///
@@ -38,13 +46,13 @@ pub fn construct_result_error(error: &str, route: &str) -> ApiError {
     ApiError::new(
         StatusCode::INTERNAL_SERVER_ERROR,
         ApiErrorType::Generic(error.to_string()),
-        "0".to_string(),
+        String::new(),
         route.to_string(),
     )
 }
 
 /// API Error types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ApiErrorType {
     Generic(String),
     InvalidSignature,
@@ -60,6 +68,144 @@ pub enum ApiErrorType {
     CacheQueryFailed,
     ValueIdNotFound,
     DataNotFound,
+    RequestExpired,
+    NonceReplayed,
+    AddressMismatch,
+    RouteNotFound,
+    MethodNotAllowed,
+    InvalidRequestBody(String),
+    InvalidQuery,
+    MissingHeader(String),
+    InvalidHeader(String),
+    MissingCookie(String),
+    MissingConnectionUpgrade,
+    LengthRequired,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    CorsForbidden(String),
+    FilePermissionDenied,
+}
+
+impl ApiErrorType {
+    /// Stable machine-readable code for the error, emitted in error replies
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiErrorType::Generic(_) => "GENERIC",
+            ApiErrorType::InvalidSignature => "INVALID_SIGNATURE",
+            ApiErrorType::DBInsertionFailed => "DB_INSERTION_FAILED",
+            ApiErrorType::CacheInsertionFailed => "CACHE_INSERTION_FAILED",
+            ApiErrorType::CuckooFilterInsertionFailed => "CUCKOO_FILTER_INSERTION_FAILED",
+            ApiErrorType::CuckooFilterLookupFailed => "CUCKOO_FILTER_LOOKUP_FAILED",
+            ApiErrorType::DataSerializationFailed => "DATA_SERIALIZATION_FAILED",
+            ApiErrorType::DataDeserializationFailed => "DATA_DESERIALIZATION_FAILED",
+            ApiErrorType::DBQueryFailed => "DB_QUERY_FAILED",
+            ApiErrorType::CacheDeleteFailed => "CACHE_DELETE_FAILED",
+            ApiErrorType::ValueDeleteFailed => "VALUE_DELETE_FAILED",
+            ApiErrorType::CacheQueryFailed => "CACHE_QUERY_FAILED",
+            ApiErrorType::ValueIdNotFound => "VALUE_ID_NOT_FOUND",
+            ApiErrorType::DataNotFound => "DATA_NOT_FOUND",
+            ApiErrorType::RequestExpired => "REQUEST_EXPIRED",
+            ApiErrorType::NonceReplayed => "NONCE_REPLAYED",
+            ApiErrorType::AddressMismatch => "ADDRESS_MISMATCH",
+            ApiErrorType::RouteNotFound => "ROUTE_NOT_FOUND",
+            ApiErrorType::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ApiErrorType::InvalidRequestBody(_) => "INVALID_REQUEST_BODY",
+            ApiErrorType::InvalidQuery => "INVALID_QUERY",
+            ApiErrorType::MissingHeader(_) => "MISSING_HEADER",
+            ApiErrorType::InvalidHeader(_) => "INVALID_HEADER",
+            ApiErrorType::MissingCookie(_) => "MISSING_COOKIE",
+            ApiErrorType::MissingConnectionUpgrade => "MISSING_CONNECTION_UPGRADE",
+            ApiErrorType::LengthRequired => "LENGTH_REQUIRED",
+            ApiErrorType::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ApiErrorType::UnsupportedMediaType => "UNSUPPORTED_MEDIA_TYPE",
+            ApiErrorType::CorsForbidden(_) => "CORS_FORBIDDEN",
+            ApiErrorType::FilePermissionDenied => "FILE_PERMISSION_DENIED",
+        }
+    }
+
+    /// Rebuilds an error type from the `code` and `reason` of an error reply
+    ///
+    /// ### Arguments
+    ///
+    /// * `code` - Error code, as given by `code`
+    /// * `reason` - Error message, used for the payload of variants carrying one
+    pub fn from_code(code: &str, reason: &str) -> Option<Self> {
+        let with_payload = |variant: fn(String) -> ApiErrorType| {
+            let prefix = variant(String::new()).to_string();
+            variant(reason.strip_prefix(&prefix).unwrap_or(reason).to_owned())
+        };
+
+        Some(match code {
+            "GENERIC" => with_payload(ApiErrorType::Generic),
+            "INVALID_SIGNATURE" => ApiErrorType::InvalidSignature,
+            "DB_INSERTION_FAILED" => ApiErrorType::DBInsertionFailed,
+            "CACHE_INSERTION_FAILED" => ApiErrorType::CacheInsertionFailed,
+            "CUCKOO_FILTER_INSERTION_FAILED" => ApiErrorType::CuckooFilterInsertionFailed,
+            "CUCKOO_FILTER_LOOKUP_FAILED" => ApiErrorType::CuckooFilterLookupFailed,
+            "DATA_SERIALIZATION_FAILED" => ApiErrorType::DataSerializationFailed,
+            "DATA_DESERIALIZATION_FAILED" => ApiErrorType::DataDeserializationFailed,
+            "DB_QUERY_FAILED" => ApiErrorType::DBQueryFailed,
+            "CACHE_DELETE_FAILED" => ApiErrorType::CacheDeleteFailed,
+            "VALUE_DELETE_FAILED" => ApiErrorType::ValueDeleteFailed,
+            "CACHE_QUERY_FAILED" => ApiErrorType::CacheQueryFailed,
+            "VALUE_ID_NOT_FOUND" => ApiErrorType::ValueIdNotFound,
+            "DATA_NOT_FOUND" => ApiErrorType::DataNotFound,
+            "REQUEST_EXPIRED" => ApiErrorType::RequestExpired,
+            "NONCE_REPLAYED" => ApiErrorType::NonceReplayed,
+            "ADDRESS_MISMATCH" => ApiErrorType::AddressMismatch,
+            "ROUTE_NOT_FOUND" => ApiErrorType::RouteNotFound,
+            "METHOD_NOT_ALLOWED" => ApiErrorType::MethodNotAllowed,
+            "INVALID_REQUEST_BODY" => with_payload(ApiErrorType::InvalidRequestBody),
+            "INVALID_QUERY" => ApiErrorType::InvalidQuery,
+            "MISSING_HEADER" => with_payload(ApiErrorType::MissingHeader),
+            "INVALID_HEADER" => with_payload(ApiErrorType::InvalidHeader),
+            "MISSING_COOKIE" => with_payload(ApiErrorType::MissingCookie),
+            "MISSING_CONNECTION_UPGRADE" => ApiErrorType::MissingConnectionUpgrade,
+            "LENGTH_REQUIRED" => ApiErrorType::LengthRequired,
+            "PAYLOAD_TOO_LARGE" => ApiErrorType::PayloadTooLarge,
+            "UNSUPPORTED_MEDIA_TYPE" => ApiErrorType::UnsupportedMediaType,
+            "CORS_FORBIDDEN" => with_payload(ApiErrorType::CorsForbidden),
+            "FILE_PERMISSION_DENIED" => ApiErrorType::FilePermissionDenied,
+            _ => return None,
+        })
+    }
+
+    /// Default HTTP status code for the error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiErrorType::InvalidSignature
+            | ApiErrorType::InvalidRequestBody(_)
+            | ApiErrorType::InvalidQuery
+            | ApiErrorType::MissingHeader(_)
+            | ApiErrorType::InvalidHeader(_)
+            | ApiErrorType::MissingCookie(_)
+            | ApiErrorType::MissingConnectionUpgrade => StatusCode::BAD_REQUEST,
+            ApiErrorType::RequestExpired
+            | ApiErrorType::NonceReplayed
+            | ApiErrorType::AddressMismatch => StatusCode::UNAUTHORIZED,
+            ApiErrorType::CorsForbidden(_) | ApiErrorType::FilePermissionDenied => {
+                StatusCode::FORBIDDEN
+            }
+            ApiErrorType::CuckooFilterLookupFailed
+            | ApiErrorType::ValueIdNotFound
+            | ApiErrorType::DataNotFound
+            | ApiErrorType::RouteNotFound => StatusCode::NOT_FOUND,
+            ApiErrorType::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ApiErrorType::LengthRequired => StatusCode::LENGTH_REQUIRED,
+            ApiErrorType::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiErrorType::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiErrorType::Generic(_)
+            | ApiErrorType::DBInsertionFailed
+            | ApiErrorType::CacheInsertionFailed
+            | ApiErrorType::CuckooFilterInsertionFailed
+            | ApiErrorType::DataSerializationFailed
+            | ApiErrorType::DataDeserializationFailed
+            | ApiErrorType::DBQueryFailed
+            | ApiErrorType::CacheDeleteFailed
+            | ApiErrorType::ValueDeleteFailed
+            | ApiErrorType::CacheQueryFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 impl std::fmt::Display for ApiErrorType {
@@ -84,6 +230,30 @@ impl std::fmt::Display for ApiErrorType {
             ApiErrorType::ValueDeleteFailed => write!(f, "Value deletion failed"),
             ApiErrorType::DataNotFound => write!(f, "Data not found"),
             ApiErrorType::ValueIdNotFound => write!(f, "Value ID not found"),
+            ApiErrorType::RequestExpired => {
+                write!(f, "Request timestamp missing or outside the allowed window")
+            }
+            ApiErrorType::NonceReplayed => write!(f, "Request nonce missing or already used"),
+            ApiErrorType::AddressMismatch => {
+                write!(f, "Address is not derived from the public key")
+            }
+            ApiErrorType::RouteNotFound => write!(f, "Route not found"),
+            ApiErrorType::MethodNotAllowed => write!(f, "HTTP method not allowed"),
+            ApiErrorType::InvalidRequestBody(message) => {
+                write!(f, "Invalid request body: {message}")
+            }
+            ApiErrorType::InvalidQuery => write!(f, "Invalid query string"),
+            ApiErrorType::MissingHeader(name) => write!(f, "Missing request header: {name}"),
+            ApiErrorType::InvalidHeader(name) => write!(f, "Invalid request header: {name}"),
+            ApiErrorType::MissingCookie(name) => write!(f, "Missing request cookie: {name}"),
+            ApiErrorType::MissingConnectionUpgrade => {
+                write!(f, "Connection header did not include 'upgrade'")
+            }
+            ApiErrorType::LengthRequired => write!(f, "A content-length header is required"),
+            ApiErrorType::PayloadTooLarge => write!(f, "Request payload too large"),
+            ApiErrorType::UnsupportedMediaType => write!(f, "Unsupported content type"),
+            ApiErrorType::CorsForbidden(message) => write!(f, "CORS request forbidden: {message}"),
+            ApiErrorType::FilePermissionDenied => write!(f, "File permission denied"),
         }
     }
 }
@@ -1,5 +1,7 @@
 use crate::api::errors::ApiErrorType;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tracing::warn;
 use warp::hyper::StatusCode;
 
 //====== JSON HANDLING ======//
@@ -78,6 +80,11 @@ impl<'a> CallResponse<'a> {
         self.into_err(StatusCode::BAD_REQUEST, api_error_type)
     }
 
+    /// Error reply using the default status code of the error type
+    pub fn into_api_err(self, api_error_type: ApiErrorType) -> Result<JsonReply, JsonReply> {
+        self.into_err(api_error_type.status_code(), api_error_type)
+    }
+
     pub fn into_err_with_data(
         self,
         status: StatusCode,
@@ -100,7 +107,7 @@ impl<'a> CallResponse<'a> {
     }
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum APIResponseStatus {
     Success,
     Error,
@@ -120,65 +127,266 @@ impl std::fmt::Display for APIResponseStatus {
     }
 }
 
+/// Envelope of every API reply
+///
+/// `id` and `code` are omitted from the JSON when not set, so replies keep the
+/// `{status, reason, route, content}` shape clients already parse.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    /// Request ID, see `api::utils::with_request_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub status: APIResponseStatus,
+    pub reason: String,
+    pub route: String,
+    /// Machine-readable error code, see `ApiErrorType::code`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub content: T,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn new(status: APIResponseStatus, reason: &str, route: &str, content: T) -> Self {
+        ApiResponse {
+            id: None,
+            status,
+            reason: reason.to_owned(),
+            route: route.to_owned(),
+            code: None,
+            content,
+        }
+    }
+
+    pub fn success(route: &str, reason: &str, content: T) -> Self {
+        Self::new(APIResponseStatus::Success, reason, route, content)
+    }
+
+    pub fn error(route: &str, error_type: &ApiErrorType, content: T) -> Self {
+        Self::new(
+            APIResponseStatus::Error,
+            &format!("{error_type}"),
+            route,
+            content,
+        )
+        .with_error_code(error_type.code())
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    pub fn with_error_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_owned());
+        self
+    }
+}
+
+impl<T: Serialize> ApiResponse<T> {
+    /// Serializes the envelope into a JSON reply with the given status code
+    ///
+    /// ### Arguments
+    ///
+    /// * `status_code` - HTTP status code of the reply
+    pub fn into_reply(self, status_code: StatusCode) -> JsonReply {
+        json_serialize_embed(self).with_code(status_code)
+    }
+}
+
+impl JsonReply {
+    /// Content of the reply as raw JSON, `null` if it is not valid JSON
+    fn into_raw_value(self) -> Box<RawValue> {
+        String::from_utf8(self.data)
+            .ok()
+            .and_then(|data| RawValue::from_string(data).ok())
+            .unwrap_or_else(|| {
+                warn!("Reply content is not valid JSON, replacing with null");
+                RawValue::from_string("null".to_owned()).unwrap_or_default()
+            })
+    }
+}
+
 /// Common reply structure for API calls
 ///
 /// ### Arguments
 ///
-/// * `id` - The ID of the API call. Provided by client
 /// * `status` - The status of the API call.
 /// * `reason` - The reason for the API call's failure, if any
 /// * `route` - The route of the API call, as client confirmation
-/// * `json_content` - Content of the API call, as JSON
+/// * `content` - Content of the API call, as JSON
 pub fn common_reply(
     status: APIResponseStatus,
     reason: &str,
     route: &str,
     content: JsonReply,
 ) -> JsonReply {
-    let status = format!("{status}");
-    json_embed(&[
-        b"{\"status\":\"",
-        status.as_bytes(),
-        b"\",\"reason\":\"",
-        reason.as_bytes(),
-        b"\",\"route\":\"",
-        route.as_bytes(),
-        b"\",\"content\":",
-        &content.data,
-        b"}",
-    ])
+    ApiResponse::new(status, reason, route, content.into_raw_value()).into_reply(StatusCode::OK)
 }
 
 /// Handles common success replies
 ///
 /// ### Arguments
 ///
-/// * `id` - The ID of the API call. Provided by client
 /// * `route` - The route of the API call, as client confirmation
+/// * `reason` - The reason for the reply
 /// * `json_content` - Content of the API call, as JSON
 pub fn common_success_reply(route: &str, reason: &str, json_content: JsonReply) -> JsonReply {
-    common_reply(APIResponseStatus::Success, reason, route, json_content).with_code(StatusCode::OK)
+    ApiResponse::success(route, reason, json_content.into_raw_value()).into_reply(StatusCode::OK)
 }
 
 /// Handles common error replies
 ///
 /// ### Arguments
 ///
-/// * `id` - The ID of the API call. Provided by client
-/// * `error` - The reason for the API call's failure
+/// * `status` - HTTP status code of the reply
+/// * `error_type` - The reason for the API call's failure, also emitted as its `code`
 /// * `route` - The route of the API call, as client confirmation
-/// * `json_content` - Content of the API call, as JSON
+/// * `data` - Content of the API call, as JSON
 pub fn common_error_reply(
     status: StatusCode,
     error_type: ApiErrorType,
     route: &str,
     data: JsonReply,
 ) -> JsonReply {
-    common_reply(
-        APIResponseStatus::Error,
-        &format!("{error_type}"),
-        route,
-        data,
-    )
-    .with_code(status)
+    ApiResponse::error(route, &error_type, data.into_raw_value()).into_reply(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    fn reply_parts(reply: JsonReply) -> (StatusCode, serde_json::Value) {
+        let status = reply.status_code;
+        (status, serde_json::from_slice(&reply.data).unwrap())
+    }
+
+    #[test]
+    fn should_pick_status_and_code_from_error_type() {
+        //
+        // Arrange
+        //
+        let call = CallResponse::new("get_data");
+
+        //
+        // Act
+        //
+        let reply = call.into_api_err(ApiErrorType::DataNotFound).unwrap_err();
+        let (status, body) = reply_parts(reply);
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["status"], "Error");
+        assert_eq!(body["code"], "DATA_NOT_FOUND");
+        assert_eq!(body["route"], "get_data");
+        assert_eq!(body["content"], "null");
+    }
+
+    #[test]
+    fn should_not_emit_code_on_success() {
+        //
+        // Arrange
+        //
+        let call = CallResponse::new("get_data");
+
+        //
+        // Act
+        //
+        let reply = call
+            .into_ok("Data retrieved", json_serialize_embed(1))
+            .unwrap();
+        let response = reply.clone().into_response();
+        let (status, body) = reply_parts(reply);
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body["reason"], "Data retrieved");
+        assert_eq!(body["content"], 1);
+        assert!(body.get("code").is_none());
+    }
+
+    #[test]
+    fn should_keep_envelope_wire_format() {
+        //
+        // Arrange
+        //
+        let content = json_serialize_embed(vec![1, 2]);
+
+        //
+        // Act
+        //
+        let reply = common_success_reply("get_data", "Data retrieved", content);
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            std::str::from_utf8(&reply.data).unwrap(),
+            r#"{"status":"Success","reason":"Data retrieved","route":"get_data","content":[1,2]}"#
+        );
+    }
+
+    #[test]
+    fn should_escape_reason_and_route() {
+        //
+        // Arrange
+        //
+        let error_type = ApiErrorType::Generic(r#"bad "value","injected":true"#.to_owned());
+
+        //
+        // Act
+        //
+        let reply = common_error_reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error_type,
+            "route\"",
+            json_serialize_embed("null"),
+        );
+        let envelope: ApiResponse<serde_json::Value> = serde_json::from_slice(&reply.data).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&reply.data).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(envelope.status, APIResponseStatus::Error);
+        assert_eq!(
+            envelope.reason,
+            r#"Generic error: bad "value","injected":true"#
+        );
+        assert_eq!(envelope.route, "route\"");
+        assert_eq!(envelope.code.as_deref(), Some("GENERIC"));
+        assert!(body.get("injected").is_none());
+    }
+
+    #[test]
+    fn should_deserialize_typed_envelope() {
+        //
+        // Arrange
+        //
+        let reply = ApiResponse::success("get_data", "Data retrieved", vec![1_u8, 2])
+            .with_id("req-1")
+            .into_reply(StatusCode::OK);
+
+        //
+        // Act
+        //
+        let envelope: ApiResponse<Vec<u8>> = serde_json::from_slice(&reply.data).unwrap();
+        let legacy: ApiResponse<u8> =
+            serde_json::from_str(r#"{"status":"Success","reason":"","route":"r","content":3}"#)
+                .unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(envelope.id.as_deref(), Some("req-1"));
+        assert_eq!(envelope.content, vec![1, 2]);
+        assert_eq!(legacy.id, None);
+        assert_eq!(legacy.code, None);
+        assert_eq!(legacy.content, 3);
+    }
 }
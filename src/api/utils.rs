@@ -1,11 +1,22 @@
-use crate::api::errors::ApiErrorType;
-use crate::api::responses::JsonReply;
-use crate::utils::validate_signature;
+use crate::api::auth::{sig_verify_middleware_with_config, SigVerifyConfig};
+use crate::api::errors::{ApiError, ApiErrorType};
+use crate::api::responses::{ApiResponse, JsonReply};
+use crate::crypto::generate_random;
 use std::convert::Infallible;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+use warp::http::header::{HeaderMap, HeaderName, HeaderValue};
+use warp::http::uri::Authority;
+use warp::http::Method;
 use warp::{Filter, Future, Rejection, Reply};
 
 impl warp::reject::Reject for ApiErrorType {}
-use tracing::{debug, warn};
+
+/// Header carrying the request ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Maximum length of a client provided request ID
+pub const MAX_REQUEST_ID_LEN: usize = 128;
 
 /// Clone component/struct to use in route
 ///
@@ -18,110 +29,394 @@ pub fn with_node_component<T: Clone + Send>(
     warp::any().map(move || comp.clone())
 }
 
-fn cors_builder(methods: Vec<&str>) -> warp::cors::Builder {
-    warp::cors()
-        .allow_any_origin()
-        .allow_headers(vec![
-            "Accept",
-            "User-Agent",
-            "Sec-Fetch-Mode",
-            "Referer",
-            "Origin",
-            "Access-Control-Request-Method",
-            "Access-Control-Request-Headers",
-            "Access-Control-Allow-Origin",
-            "Access-Control-Allow-Headers",
-            "Content-Type",
-            "public_key",
-            "address",
-            "signature",
-            "dnt",
-            "sec-ch-ua",
-            "sec-ch-ua-mobile",
-            "sec-ch-ua-platform",
-        ])
-        .allow_methods(methods)
+/// Request headers allowed by `CorsConfig::default`
+pub const DEFAULT_CORS_HEADERS: &[&str] = &[
+    "Accept",
+    "User-Agent",
+    "Sec-Fetch-Mode",
+    "Referer",
+    "Origin",
+    "Access-Control-Request-Method",
+    "Access-Control-Request-Headers",
+    "Access-Control-Allow-Origin",
+    "Access-Control-Allow-Headers",
+    "Content-Type",
+    "public_key",
+    "address",
+    "signature",
+    "algorithm",
+    "authenticator_data",
+    "client_data_json",
+    "timestamp",
+    "X-Request-Id",
+    "nonce",
+    "dnt",
+    "sec-ch-ua",
+    "sec-ch-ua-mobile",
+    "sec-ch-ua-platform",
+];
+
+/// Response headers exposed by every `CorsConfig`
+pub const DEFAULT_CORS_EXPOSED_HEADERS: &[&str] = &["X-Request-Id"];
+
+/// CORS settings for a route, built into a warp CORS filter with `build`.
+///
+/// The default allows any origin, no credentials and the `DEFAULT_CORS_HEADERS`,
+/// exposes the `DEFAULT_CORS_EXPOSED_HEADERS`, with only `OPTIONS` as method.
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    /// Allowed origins, e.g. `https://app.a-block.io`. Any origin when `None`
+    pub allowed_origins: Option<Vec<String>>,
+    /// Allow cookies and auth headers, only together with `allowed_origins`
+    pub allow_credentials: bool,
+    /// How long preflight results may be cached, in seconds
+    pub max_age: Option<u64>,
+    /// Response headers readable by the browser on top of `DEFAULT_CORS_EXPOSED_HEADERS`
+    pub exposed_headers: Vec<String>,
+    /// Allowed methods, `OPTIONS` is always added
+    pub methods: Vec<String>,
+    /// Request headers allowed on top of `DEFAULT_CORS_HEADERS`
+    pub extra_headers: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Default settings allowing the given methods
+    ///
+    /// ### Arguments
+    ///
+    /// * `methods` - Allowed methods, e.g. `["PUT", "PATCH"]`
+    pub fn new(methods: &[&str]) -> Self {
+        CorsConfig::default().with_methods(methods)
+    }
+
+    pub fn with_methods(mut self, methods: &[&str]) -> Self {
+        self.methods.extend(methods.iter().map(|m| m.to_string()));
+        self
+    }
+
+    /// Only allows the given origins instead of any origin
+    pub fn with_allowed_origins(mut self, origins: &[&str]) -> Self {
+        self.allowed_origins
+            .get_or_insert_with(Vec::new)
+            .extend(origins.iter().map(|o| o.to_string()));
+        self
+    }
+
+    pub fn with_credentials(mut self) -> Self {
+        self.allow_credentials = true;
+        self
+    }
+
+    pub fn with_max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn with_exposed_headers(mut self, headers: &[&str]) -> Self {
+        self.exposed_headers
+            .extend(headers.iter().map(|h| h.to_string()));
+        self
+    }
+
+    pub fn with_extra_headers(mut self, headers: &[&str]) -> Self {
+        self.extra_headers
+            .extend(headers.iter().map(|h| h.to_string()));
+        self
+    }
+
+    /// Builds the warp CORS filter, or `None` if an origin, method or header is invalid,
+    /// or credentials are allowed without an origin allow-list
+    pub fn build(&self) -> Option<warp::cors::Builder> {
+        let methods: Vec<&str> = self
+            .methods
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once("OPTIONS"))
+            .collect();
+        let headers: Vec<&str> = DEFAULT_CORS_HEADERS
+            .iter()
+            .copied()
+            .chain(self.extra_headers.iter().map(String::as_str))
+            .collect();
+        let exposed_headers: Vec<&str> = DEFAULT_CORS_EXPOSED_HEADERS
+            .iter()
+            .copied()
+            .chain(self.exposed_headers.iter().map(String::as_str))
+            .collect();
+
+        // warp panics on invalid values, so check them first
+        let valid_header = |h: &&str| HeaderName::from_bytes(h.as_bytes()).is_ok();
+        let valid = methods
+            .iter()
+            .all(|m| Method::from_bytes(m.as_bytes()).is_ok())
+            && headers.iter().all(valid_header)
+            && exposed_headers.iter().all(valid_header)
+            && self
+                .allowed_origins
+                .iter()
+                .flatten()
+                .all(|o| is_valid_origin(o));
+        if !valid {
+            warn!("Invalid CORS configuration: {self:?}");
+            return None;
+        }
+        // warp echoes any origin, which would let every site make credentialed requests
+        if self.allow_credentials && self.allowed_origins.is_none() {
+            warn!("CORS credentials need an origin allow-list");
+            return None;
+        }
+
+        let mut cors = warp::cors()
+            .allow_headers(headers)
+            .allow_methods(methods)
+            .expose_headers(exposed_headers)
+            .allow_credentials(self.allow_credentials);
+        cors = match &self.allowed_origins {
+            Some(origins) => cors.allow_origins(origins.iter().map(String::as_str)),
+            None => cors.allow_any_origin(),
+        };
+        if let Some(max_age) = self.max_age {
+            cors = cors.max_age(Duration::from_secs(max_age));
+        }
+
+        Some(cors)
+    }
+}
+
+/// Checks an origin has the `scheme://host[:port]` form warp expects
+///
+/// ### Arguments
+///
+/// * `origin` - Origin to check
+fn is_valid_origin(origin: &str) -> bool {
+    match origin.split_once("://") {
+        Some((scheme, authority)) => {
+            !scheme.is_empty()
+                && !authority.is_empty()
+                && authority.parse::<Authority>().is_ok()
+                && !authority.contains(['/', '@'])
+        }
+        None => false,
+    }
+}
+
+fn cors_builder(methods: &[&str]) -> warp::cors::Builder {
+    // Callers pass static, valid methods, so building cannot fail
+    CorsConfig::new(methods).build().unwrap_or_else(warp::cors)
 }
 
 /// Easy and simple POST CORS
 pub fn post_cors() -> warp::cors::Builder {
-    cors_builder(vec!["POST", "OPTIONS"])
+    cors_builder(&["POST"])
 }
 
 /// Easy and simple GET CORS
 pub fn get_cors() -> warp::cors::Builder {
-    cors_builder(vec!["GET", "OPTIONS"])
+    cors_builder(&["GET"])
 }
 
 /// Easy and simple DELETE CORS
 pub fn del_cors() -> warp::cors::Builder {
-    cors_builder(vec!["DELETE", "OPTIONS"])
+    cors_builder(&["DELETE"])
 }
 
 /// Middleware filter to handle signature verification
 pub fn sig_verify_middleware() -> impl Filter<Extract = ((),), Error = Rejection> + Clone {
-    warp::path::full()
-        .and(warp::header::headers_cloned())
-        .and_then(
-            move |_: warp::path::FullPath, headers: warp::hyper::HeaderMap| {
-                debug!("Validating signature");
-
-                async move {
-                    let public_key = headers
-                        .get("public_key")
-                        .and_then(|n| n.to_str().ok())
-                        .unwrap_or_default();
-
-                    debug!("public_key: {:?}", public_key);
-
-                    let address = headers
-                        .get("address")
-                        .and_then(|n| n.to_str().ok())
-                        .unwrap_or_default();
-
-                    debug!("address: {:?}", address);
+    sig_verify_middleware_with_config(SigVerifyConfig::default())
+}
 
-                    let signature = headers
-                        .get("signature")
-                        .and_then(|n| n.to_str().ok())
-                        .unwrap_or_default();
+/// Maps a rejection to an `ApiError`, with the route and request ID left empty if unknown
+///
+/// ### Arguments
+///
+/// * `err` - Rejection error
+fn rejection_error(err: &Rejection) -> ApiError {
+    use warp::reject;
 
-                    debug!("signature: {:?}", signature);
+    if let Some(error) = err.find::<ApiError>() {
+        return error.clone();
+    }
 
-                    if validate_signature(public_key, address, signature) {
-                        debug!("Signature is valid");
+    let error_type = if err.is_not_found() {
+        ApiErrorType::RouteNotFound
+    } else if let Some(error_type) = err.find::<ApiErrorType>() {
+        error_type.clone()
+    } else if let Some(e) = err.find::<warp::body::BodyDeserializeError>() {
+        ApiErrorType::InvalidRequestBody(e.to_string())
+    } else if err.find::<reject::InvalidQuery>().is_some() {
+        ApiErrorType::InvalidQuery
+    } else if let Some(e) = err.find::<reject::MissingHeader>() {
+        ApiErrorType::MissingHeader(e.name().to_string())
+    } else if let Some(e) = err.find::<reject::InvalidHeader>() {
+        ApiErrorType::InvalidHeader(e.name().to_string())
+    } else if let Some(e) = err.find::<reject::MissingCookie>() {
+        ApiErrorType::MissingCookie(e.name().to_string())
+    } else if err.find::<warp::ws::MissingConnectionUpgrade>().is_some() {
+        ApiErrorType::MissingConnectionUpgrade
+    } else if err.find::<reject::MethodNotAllowed>().is_some() {
+        ApiErrorType::MethodNotAllowed
+    } else if err.find::<reject::LengthRequired>().is_some() {
+        ApiErrorType::LengthRequired
+    } else if err.find::<reject::PayloadTooLarge>().is_some() {
+        ApiErrorType::PayloadTooLarge
+    } else if err.find::<reject::UnsupportedMediaType>().is_some() {
+        ApiErrorType::UnsupportedMediaType
+    } else if let Some(e) = err.find::<warp::cors::CorsForbidden>() {
+        ApiErrorType::CorsForbidden(e.to_string())
+    } else if let Some(error_type) = private_warp_rejection(err) {
+        error_type
+    } else {
+        // Details are logged by `rejection_reply`, never sent to the client
+        ApiErrorType::Generic("Internal Server Error".to_string())
+    };
 
-                        // Proceed to the next filter/handler
-                        return Ok(());
-                    }
+    ApiError::new(
+        error_type.status_code(),
+        error_type,
+        String::new(),
+        String::new(),
+    )
+}
 
-                    warn!("Invalid signature");
-                    Err(warp::reject::custom(ApiErrorType::InvalidSignature))
-                }
-            },
-        )
+/// Maps the built-in warp rejections whose types warp keeps private,
+/// and which can therefore only be recognised by their debug output
+///
+/// ### Arguments
+///
+/// * `err` - Rejection error
+fn private_warp_rejection(err: &Rejection) -> Option<ApiErrorType> {
+    let debug = format!("{err:?}");
+    if debug.contains("BodyReadError(") {
+        Some(ApiErrorType::InvalidRequestBody(
+            "Failed to read request body".to_string(),
+        ))
+    } else if debug.contains("FilePermissionError") {
+        Some(ApiErrorType::FilePermissionDenied)
+    } else {
+        None
+    }
 }
 
-/// Rejection handler
+/// Rejection handler, replying with the standard JSON error envelope
 ///
 /// ### Arguments
 ///
 /// * `err` - Rejection error
 pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
-    if let Some(ApiErrorType::InvalidSignature) = err.find() {
-        // Handle invalid signature error here
-        Ok(warp::reply::with_status(
-            "Invalid signature",
-            warp::http::StatusCode::BAD_REQUEST,
-        ))
+    Ok(rejection_reply(&err, ""))
+}
+
+/// Builds the JSON error reply for a rejection
+///
+/// ### Arguments
+///
+/// * `err` - Rejection error
+/// * `request_id` - Request ID to send when the error doesn't carry one, omitted when empty
+fn rejection_reply(err: &Rejection, request_id: &str) -> JsonReply {
+    let error = rejection_error(err);
+
+    if error.code.is_server_error() {
+        error!("Internal Server Error: {err:?}");
     } else {
-        println!("Internal Server Error: {err:?}");
-        // For other kinds of rejections, return a generic error
-        Ok(warp::reply::with_status(
-            "Internal Server Error",
-            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+        debug!("Request rejected with {}: {}", error.code, error.message);
     }
+
+    let id = match error.id.as_str() {
+        "" => request_id,
+        id => id,
+    };
+    let reply = ApiResponse::error(&error.route, &error.message, "null");
+    match id {
+        "" => reply.into_reply(error.code),
+        id => reply.with_id(id).into_reply(error.code),
+    }
+}
+
+/// Request ID filter, reading the `X-Request-Id` header or generating a new ID
+///
+/// Client provided IDs longer than `MAX_REQUEST_ID_LEN` or containing characters
+/// other than ASCII alphanumerics, `-`, `_`, `.` and `:` are replaced
+fn request_id() -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(|headers: HeaderMap| {
+        let id = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map(str::to_owned)
+            .unwrap_or_else(|| hex::encode(generate_random::<16>()));
+
+        tracing::Span::current().record("request_id", id.as_str());
+        id
+    })
+}
+
+/// Reply built once the request ID is known, extracted by routes wrapped with `with_request_id`
+pub type RequestIdReply = Box<dyn FnOnce(String) -> warp::reply::Response + Send>;
+
+/// Reply builder for routes wrapped with `with_request_id`
+///
+/// ### Arguments
+///
+/// * `reply` - Builds the reply from the request ID, e.g. setting it with `ApiResponse::with_id`
+pub fn reply_with_id<R: Reply>(reply: impl FnOnce(String) -> R + Send + 'static) -> RequestIdReply {
+    Box::new(|id| reply(id).into_response())
+}
+
+/// Wraps routes so every reply carries a request ID
+///
+/// The ID is read from the `X-Request-Id` header or generated once per request.
+/// Routes extract a `RequestIdReply`, see `reply_with_id`, which is called with the ID
+/// so handlers can set it on their `ApiResponse` and in their logs. The ID is also
+/// recorded on a `request` tracing span and echoed in the `X-Request-Id` response header.
+/// Rejections are handled as with `handle_rejection`, with the ID in the envelope.
+///
+/// ### Arguments
+///
+/// * `routes` - Routes to wrap
+pub fn with_request_id<F>(
+    routes: F,
+) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (RequestIdReply,), Error = Rejection> + Clone + Send + Sync + 'static,
+{
+    let routes = routes
+        .map(|reply: RequestIdReply| Ok(reply))
+        .recover(|err: Rejection| async move { Ok::<_, Infallible>(Err(err)) })
+        .unify();
+
+    request_id()
+        .and(routes)
+        .map(|id: String, res: Result<RequestIdReply, Rejection>| {
+            let mut res = match res {
+                Ok(reply) => reply(id.clone()),
+                Err(err) => rejection_reply(&err, &id).into_response(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            res
+        })
+        .with(warp::trace(|info| {
+            tracing::info_span!(
+                "request",
+                method = %info.method(),
+                path = info.path(),
+                request_id = tracing::field::Empty,
+            )
+        }))
+}
+
+/// Checks whether a client provided request ID is safe to echo
+///
+/// ### Arguments
+///
+/// * `id` - Request ID
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
 }
 
 /// Map API result to warp reply
@@ -135,3 +430,387 @@ pub fn map_api_res(
     use futures::future::TryFutureExt;
     r.map_ok_or_else(Ok, Ok)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::errors::construct_result_error;
+    use crate::api::responses::json_embed;
+    use warp::hyper::StatusCode;
+
+    async fn rejection_body(
+        filter: impl Filter<Extract = impl Reply, Error = Rejection> + Clone + Send + Sync + 'static,
+        request: warp::test::RequestBuilder,
+    ) -> (StatusCode, serde_json::Value) {
+        let res = request.reply(&filter.recover(handle_rejection)).await;
+        (res.status(), serde_json::from_slice(res.body()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn should_reply_json_for_unknown_route() {
+        //
+        // Arrange
+        //
+        let filter = warp::path("known").map(warp::reply);
+        let request = warp::test::request().path("/unknown");
+
+        //
+        // Act
+        //
+        let (status, body) = rejection_body(filter, request).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["status"], "Error");
+        assert_eq!(body["reason"], "Route not found");
+        assert_eq!(body["code"], "ROUTE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn should_map_warp_rejections_to_status_codes() {
+        //
+        // Arrange
+        //
+        let filter = warp::post()
+            .and(warp::body::content_length_limit(8))
+            .and(warp::body::json())
+            .map(|_: serde_json::Value| warp::reply());
+        let cookie = warp::cookie::<String>("session").map(|_| warp::reply());
+        let ws = warp::ws().map(|_| warp::reply());
+
+        //
+        // Act
+        //
+        let (method, _) = rejection_body(filter, warp::test::request().method("GET")).await;
+        let (too_large, _) = rejection_body(
+            filter,
+            warp::test::request()
+                .method("POST")
+                .body("{\"long\":\"body\"}"),
+        )
+        .await;
+        let (bad_body, body) =
+            rejection_body(filter, warp::test::request().method("POST").body("{")).await;
+        let (no_cookie, cookie_body) =
+            rejection_body(cookie, warp::test::request().header("cookie", "theme=dark")).await;
+        let (no_upgrade, upgrade_body) = rejection_body(
+            ws,
+            warp::test::request()
+                .header("connection", "keep-alive")
+                .header("upgrade", "websocket")
+                .header("sec-websocket-version", "13")
+                .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="),
+        )
+        .await;
+
+        //
+        // Assert
+        //
+        assert_eq!(method, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(too_large, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(bad_body, StatusCode::BAD_REQUEST);
+        assert!(body["reason"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid request body"));
+        assert_eq!(no_cookie, StatusCode::BAD_REQUEST);
+        assert_eq!(cookie_body["code"], "MISSING_COOKIE");
+        assert_eq!(no_upgrade, StatusCode::BAD_REQUEST);
+        assert_eq!(upgrade_body["code"], "MISSING_CONNECTION_UPGRADE");
+    }
+
+    #[tokio::test]
+    async fn should_not_leak_unhandled_rejection_details() {
+        //
+        // Arrange
+        //
+        #[derive(Debug)]
+        struct DbFailure {
+            #[allow(dead_code)]
+            db_password: &'static str,
+        }
+        impl warp::reject::Reject for DbFailure {}
+
+        let filter = warp::any().and_then(|| async {
+            Err::<String, _>(warp::reject::custom(DbFailure {
+                db_password: "hunter2",
+            }))
+        });
+
+        //
+        // Act
+        //
+        let (status, body) = rejection_body(filter, warp::test::request()).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["reason"], "Generic error: Internal Server Error");
+        assert!(!body.to_string().contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn should_send_api_error_route_and_id() {
+        //
+        // Arrange
+        //
+        let filter = warp::any().and_then(|| async {
+            Err::<String, _>(warp::reject::custom(
+                construct_result_error("Lookup failed", "get_data").with_id("req-7"),
+            ))
+        });
+
+        //
+        // Act
+        //
+        let (status, body) = rejection_body(filter, warp::test::request()).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["id"], "req-7");
+        assert_eq!(body["route"], "get_data");
+        assert_eq!(body["reason"], "Generic error: Lookup failed");
+        assert_eq!(body["content"], "null");
+    }
+
+    #[tokio::test]
+    async fn should_use_api_error_type_status_code() {
+        //
+        // Arrange
+        //
+        let filter = warp::any().and_then(|| async {
+            Err::<String, _>(warp::reject::custom(ApiErrorType::NonceReplayed))
+        });
+
+        //
+        // Act
+        //
+        let (status, body) = rejection_body(filter, warp::test::request()).await;
+
+        //
+        // Assert
+        //
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["reason"], "Request nonce missing or already used");
+    }
+
+    #[tokio::test]
+    async fn should_echo_client_request_id() {
+        //
+        // Arrange
+        //
+        let routes = warp::path("data").map(|| {
+            reply_with_id(|id| {
+                ApiResponse::success("data", "Data retrieved", 1)
+                    .with_id(&id)
+                    .into_reply(StatusCode::OK)
+            })
+        });
+        let filter = with_request_id(routes);
+
+        //
+        // Act
+        //
+        let res = warp::test::request()
+            .path("/data")
+            .header(REQUEST_ID_HEADER, "client-id-1")
+            .reply(&filter)
+            .await;
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "client-id-1");
+        assert_eq!(body["id"], "client-id-1");
+        assert_eq!(body["status"], "Success");
+        assert_eq!(body["content"], 1);
+    }
+
+    #[tokio::test]
+    async fn should_hand_generated_request_id_to_handler() {
+        //
+        // Arrange
+        //
+        let data = warp::path("data").map(|| {
+            reply_with_id(|id| {
+                ApiResponse::success("data", "Data retrieved", id.clone())
+                    .with_id(&id)
+                    .into_reply(StatusCode::OK)
+            })
+        });
+        let custom = warp::path("custom")
+            .map(|| reply_with_id(|_| json_embed(&[br#"{"status":"Success","extra":true}"#])));
+        let filter = with_request_id(data.or(custom).unify());
+
+        //
+        // Act
+        //
+        let res = warp::test::request().path("/data").reply(&filter).await;
+        let custom_res = warp::test::request().path("/custom").reply(&filter).await;
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        let header = res.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(header.len(), 32);
+        assert_eq!(body["id"], header);
+        assert_eq!(body["content"], header);
+        assert_eq!(
+            custom_res.body().as_ref(),
+            br#"{"status":"Success","extra":true}"#
+        );
+        assert!(custom_res.headers().contains_key(REQUEST_ID_HEADER));
+    }
+
+    #[tokio::test]
+    async fn should_generate_request_id_for_rejections() {
+        //
+        // Arrange
+        //
+        let routes = warp::path("data").map(|| reply_with_id(|_| warp::reply()));
+        let filter = with_request_id(routes);
+
+        //
+        // Act
+        //
+        let res = warp::test::request()
+            .path("/unknown")
+            .header(REQUEST_ID_HEADER, "bad id\"")
+            .reply(&filter)
+            .await;
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        let header = res.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(header.len(), 32);
+        assert_eq!(body["id"], header);
+        assert_eq!(body["code"], "ROUTE_NOT_FOUND");
+    }
+
+    fn preflight(origin: &str, method: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method("OPTIONS")
+            .path("/data")
+            .header("origin", origin)
+            .header("access-control-request-method", method)
+            .header("access-control-request-headers", "public_key, x-custom")
+    }
+
+    #[tokio::test]
+    async fn should_apply_cors_config() {
+        //
+        // Arrange
+        //
+        let cors = CorsConfig::new(&["PUT", "PATCH"])
+            .with_allowed_origins(&["https://app.a-block.io"])
+            .with_credentials()
+            .with_max_age(600)
+            .with_exposed_headers(&["X-Request-Id"])
+            .with_extra_headers(&["x-custom"])
+            .build()
+            .unwrap();
+        let filter = warp::path("data").map(warp::reply).with(cors);
+
+        //
+        // Act
+        //
+        let allowed = preflight("https://app.a-block.io", "PATCH")
+            .reply(&filter)
+            .await;
+        let other_origin = preflight("https://evil.example", "PATCH")
+            .reply(&filter)
+            .await;
+        let other_method = preflight("https://app.a-block.io", "DELETE")
+            .reply(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        let headers = allowed.headers();
+        assert_eq!(allowed.status(), StatusCode::OK);
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://app.a-block.io"
+        );
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-max-age"], "600");
+        assert_eq!(other_origin.status(), StatusCode::FORBIDDEN);
+        assert_eq!(other_method.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_default_to_any_origin() {
+        //
+        // Arrange
+        //
+        let filter = warp::path("data").map(warp::reply).with(get_cors());
+
+        //
+        // Act
+        //
+        let res = warp::test::request()
+            .method("OPTIONS")
+            .path("/data")
+            .header("origin", "https://any.example")
+            .header("access-control-request-method", "GET")
+            .header("access-control-request-headers", "public_key, x-request-id")
+            .reply(&filter)
+            .await;
+        let get = warp::test::request()
+            .path("/data")
+            .header("origin", "https://any.example")
+            .reply(&filter)
+            .await;
+
+        //
+        // Assert
+        //
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res
+            .headers()
+            .get("access-control-allow-credentials")
+            .is_none());
+        assert_eq!(
+            get.headers()["access-control-expose-headers"],
+            "x-request-id"
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_cors_config() {
+        //
+        // Arrange
+        //
+        let bad_origin = CorsConfig::new(&["GET"]).with_allowed_origins(&["app.a-block.io"]);
+        let bad_header = CorsConfig::new(&["GET"]).with_extra_headers(&["bad header"]);
+        let bad_method = CorsConfig::new(&["GE T"]);
+        let credentials_any_origin = CorsConfig::new(&["GET"]).with_credentials();
+
+        //
+        // Act
+        //
+        let results = [
+            bad_origin.build(),
+            bad_header.build(),
+            bad_method.build(),
+            credentials_any_origin.build(),
+        ];
+
+        //
+        // Assert
+        //
+        assert!(results.iter().all(Option::is_none));
+    }
+}
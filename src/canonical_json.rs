@@ -0,0 +1,167 @@
+use crate::crypto::sha3_256::{self, Output, Sha3_256};
+use crate::crypto::sign_ed25519::{self, PublicKey, SecretKey, Signature};
+use serde::Serialize;
+use serde_json::{Error, Number, Value};
+
+/// Largest integer a float can represent exactly
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Serializes data to canonical JSON, so the same logical value always produces the same bytes:
+///
+/// * Object keys sorted by their UTF-16 code units, as in RFC 8785
+/// * No insignificant whitespace
+/// * Floats without a fractional part written as integers, `-0` written as `0`
+///
+/// ### Arguments
+///
+/// * `data` - Data to serialize
+pub fn to_vec<T: Serialize>(data: &T) -> Result<Vec<u8>, Error> {
+    let value = serde_json::to_value(data)?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// Serializes data to a canonical JSON string
+///
+/// ### Arguments
+///
+/// * `data` - Data to serialize
+pub fn to_string<T: Serialize>(data: &T) -> Result<String, Error> {
+    let bytes = to_vec(data)?;
+    Ok(String::from_utf8(bytes).unwrap_or_default())
+}
+
+/// SHA3-256 hash of the canonical JSON encoding
+///
+/// ### Arguments
+///
+/// * `data` - Data to hash
+pub fn hash<T: Serialize>(data: &T) -> Result<Output<Sha3_256>, Error> {
+    Ok(sha3_256::digest(&to_vec(data)?))
+}
+
+/// Signs the canonical JSON encoding with Ed25519
+///
+/// ### Arguments
+///
+/// * `data` - Data to sign
+/// * `sk` - Secret key to sign with
+pub fn sign<T: Serialize>(data: &T, sk: &SecretKey) -> Result<Signature, Error> {
+    Ok(sign_ed25519::sign_detached(&to_vec(data)?, sk))
+}
+
+/// Verifies an Ed25519 signature over the canonical JSON encoding
+///
+/// ### Arguments
+///
+/// * `data` - Data that was signed
+/// * `signature` - Signature to verify
+/// * `pk` - Public key of the signer
+pub fn verify<T: Serialize>(data: &T, signature: &Signature, pk: &PublicKey) -> bool {
+    match to_vec(data) {
+        Ok(msg) => sign_ed25519::verify_detached(signature, &msg, pk),
+        Err(_) => false,
+    }
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => serde_json::to_writer(out, value)?,
+        Value::Number(number) => write_number(number, out),
+        Value::Array(values) => {
+            out.push(b'[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(value, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push(b'{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_value(value, out)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+fn write_number(number: &Number, out: &mut Vec<u8>) {
+    let normalized = match number.as_f64() {
+        Some(float) if number.is_f64() && float == 0.0 => "0".to_string(),
+        Some(float)
+            if number.is_f64() && float.fract() == 0.0 && float.abs() < MAX_SAFE_INTEGER =>
+        {
+            format!("{}", float as i64)
+        }
+        Some(float) if number.is_f64() => format!("{float}"),
+        _ => number.to_string(),
+    };
+    out.extend_from_slice(normalized.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn should_serialize_to_canonical_form() {
+        //
+        // Arrange
+        //
+        let value = json!({
+            "b": [1.0, -0.0, 2.5, 10],
+            "a": { "z": "line\nbreak", "y": null },
+            "\u{fb33}": true,
+            "\u{1f600}": false
+        });
+
+        //
+        // Act
+        //
+        let result = to_string(&value).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            result,
+            "{\"a\":{\"y\":null,\"z\":\"line\\nbreak\"},\"b\":[1,0,2.5,10],\"\u{1f600}\":false,\"\u{fb33}\":true}"
+        );
+    }
+
+    #[test]
+    fn should_hash_and_sign_equal_values_identically() {
+        //
+        // Arrange
+        //
+        let (pk, sk) = sign_ed25519::gen_keypair();
+        let first = json!({ "price": 10.0, "title": "Hello World!" });
+        let second: Value =
+            serde_json::from_str("{ \"title\": \"Hello World!\", \"price\": 10 }").unwrap();
+
+        //
+        // Act
+        //
+        let signature = sign(&first, &sk).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(hash(&first).unwrap(), hash(&second).unwrap());
+        assert!(verify(&second, &signature, &pk));
+    }
+}
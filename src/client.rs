@@ -0,0 +1,361 @@
+//! HTTP client for Valence node APIs, available with the `client` feature.
+//!
+//! Requests are signed with an Ed25519 secret key to pass the server's signature
+//! middlewares, and reply envelopes are parsed into `Result<T, ApiErrorType>`.
+//!
+//! `https://` nodes are reached over rustls, trusting the bundled webpki root
+//! certificates rather than the system store.
+
+use crate::address::Address;
+use crate::api::auth::{replay_message, sign_request_headers, unix_timestamp};
+use crate::api::errors::ApiErrorType;
+use crate::api::responses::{APIResponseStatus, ApiResponse};
+use crate::crypto::{generate_random, sign_ed25519};
+use reqwest::{Method, Url};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+/// Which server middleware the request signature is produced for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SigningMode {
+    /// Signature over the address, for `sig_verify_middleware`
+    #[default]
+    Address,
+    /// Signature over the address, timestamp and a fresh nonce, for
+    /// `sig_verify_middleware_with_config` with replay protection
+    Replay,
+    /// Signature over the whole request, for `request_sig_verify_middleware`
+    Request,
+}
+
+/// Client for a Valence node
+#[derive(Debug, Clone)]
+pub struct ValenceClient {
+    base_url: Url,
+    http: reqwest::Client,
+    secret_key: Option<sign_ed25519::SecretKey>,
+    signing_mode: SigningMode,
+}
+
+impl ValenceClient {
+    /// Creates an unsigned client, or `None` if `base_url` is not a valid URL
+    ///
+    /// ### Arguments
+    ///
+    /// * `base_url` - Base URL of the node, e.g. `http://127.0.0.1:3030`. Paths are
+    ///   joined onto it, so a base path needs a trailing slash
+    pub fn new(base_url: &str) -> Option<Self> {
+        Some(ValenceClient {
+            base_url: Url::parse(base_url).ok()?,
+            http: reqwest::Client::new(),
+            secret_key: None,
+            signing_mode: SigningMode::default(),
+        })
+    }
+
+    /// Signs every request with the given secret key
+    pub fn with_secret_key(mut self, secret_key: sign_ed25519::SecretKey) -> Self {
+        self.secret_key = Some(secret_key);
+        self
+    }
+
+    pub fn with_signing_mode(mut self, signing_mode: SigningMode) -> Self {
+        self.signing_mode = signing_mode;
+        self
+    }
+
+    /// Uses the given HTTP client, e.g. to set timeouts
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiErrorType> {
+        self.send(Method::GET, path, Vec::new()).await
+    }
+
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiErrorType> {
+        self.send(Method::DELETE, path, Vec::new()).await
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ApiErrorType> {
+        let body = serde_json::to_vec(body).map_err(|_| ApiErrorType::DataSerializationFailed)?;
+        self.send(Method::POST, path, body).await
+    }
+
+    /// Sends a request and parses the reply envelope
+    ///
+    /// ### Arguments
+    ///
+    /// * `method` - HTTP method
+    /// * `path` - Path relative to the base URL, optionally with a query string
+    /// * `body` - Request body, sent as JSON when not empty
+    pub async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Vec<u8>,
+    ) -> Result<T, ApiErrorType> {
+        let url = self
+            .base_url
+            .join(path)
+            .map_err(|e| ApiErrorType::Generic(format!("Invalid request path: {e}")))?;
+
+        let mut request = self.http.request(method.clone(), url.clone());
+        for (name, value) in self.signature_headers(&method, &url, &body)? {
+            request = request.header(name, value);
+        }
+        if !body.is_empty() {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ApiErrorType::Generic(format!("Request failed: {e}")))?;
+        let status = response.status();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ApiErrorType::Generic(format!("Failed to read reply: {e}")))?;
+        debug!("{method} {path} replied with {status}");
+
+        // Proxies and crashed nodes reply without an envelope, so only the status is known
+        let is_envelope = serde_json::from_slice::<ApiResponse<IgnoredAny>>(&bytes).is_ok();
+        if !status.is_success() && !is_envelope {
+            warn!("{method} {path} replied with {status} and no reply envelope");
+            return Err(ApiErrorType::Generic(format!("Node replied with {status}")));
+        }
+
+        parse_reply(&bytes)
+    }
+
+    /// Headers authenticating the request, empty for an unsigned client
+    fn signature_headers(
+        &self,
+        method: &Method,
+        url: &Url,
+        body: &[u8],
+    ) -> Result<Vec<(&'static str, String)>, ApiErrorType> {
+        let Some(sk) = &self.secret_key else {
+            return Ok(Vec::new());
+        };
+        let public_key = sk.public_key().ok_or(ApiErrorType::InvalidSignature)?;
+        let address = Address::from_public_key(&public_key).to_string();
+        let nonce = hex::encode(generate_random::<16>());
+
+        let headers = match self.signing_mode {
+            SigningMode::Address => {
+                let signature = sign_ed25519::sign_detached(address.as_bytes(), sk);
+                vec![
+                    ("public_key", public_key.to_string()),
+                    ("address", address),
+                    ("signature", signature.to_string()),
+                ]
+            }
+            SigningMode::Replay => {
+                let timestamp = unix_timestamp();
+                let msg = replay_message(&address, timestamp, &nonce);
+                let signature = sign_ed25519::sign_detached(msg.as_bytes(), sk);
+                vec![
+                    ("public_key", public_key.to_string()),
+                    ("address", address),
+                    ("signature", signature.to_string()),
+                    ("timestamp", timestamp.to_string()),
+                    ("nonce", nonce),
+                ]
+            }
+            SigningMode::Request => sign_request_headers(
+                sk,
+                method.as_str(),
+                url.path(),
+                url.query().unwrap_or_default(),
+                body,
+                Some(&nonce),
+            )
+            .ok_or(ApiErrorType::InvalidSignature)?,
+        };
+
+        Ok(headers)
+    }
+}
+
+/// Parses a reply envelope into its content or the error it reports
+///
+/// ### Arguments
+///
+/// * `bytes` - Reply body
+pub fn parse_reply<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ApiErrorType> {
+    let envelope: ApiResponse<serde_json::Value> = serde_json::from_slice(bytes).map_err(|e| {
+        warn!("Invalid reply envelope: {e}");
+        ApiErrorType::DataDeserializationFailed
+    })?;
+
+    if envelope.status != APIResponseStatus::Success {
+        let code = envelope.code.unwrap_or_default();
+        return Err(ApiErrorType::from_code(&code, &envelope.reason)
+            .unwrap_or(ApiErrorType::Generic(envelope.reason)));
+    }
+
+    serde_json::from_value(envelope.content).map_err(|e| {
+        warn!("Invalid reply content: {e}");
+        ApiErrorType::DataDeserializationFailed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{
+        request_sig_verify_middleware, sig_verify_middleware_with_config, ReplayProtection,
+        SigVerifyConfig,
+    };
+    use crate::api::responses::{json_serialize_embed, CallResponse};
+    use crate::api::utils::{handle_rejection, sig_verify_middleware};
+    use std::net::SocketAddr;
+    use warp::hyper::body::Bytes;
+    use warp::hyper::StatusCode;
+    use warp::Filter;
+
+    /// Serves test routes on an ephemeral port, returning the base URL
+    fn serve() -> String {
+        let address = warp::path("address")
+            .and(sig_verify_middleware())
+            .map(|_| CallResponse::new("address").into_ok("Signed", json_serialize_embed(1)))
+            .map(|res: Result<_, _>| res.unwrap_or_else(|e| e));
+
+        let replay = warp::path("replay")
+            .and(sig_verify_middleware_with_config(
+                SigVerifyConfig::new().with_replay_protection(ReplayProtection::default()),
+            ))
+            .map(|_| CallResponse::new("replay").into_ok("Signed", json_serialize_embed(2)))
+            .map(|res: Result<_, _>| res.unwrap_or_else(|e| e));
+
+        let request = warp::path("request")
+            .and(request_sig_verify_middleware(SigVerifyConfig::new()))
+            .map(|body: Bytes| {
+                let value: Vec<u32> = serde_json::from_slice(&body).unwrap_or_default();
+                CallResponse::new("request").into_ok("Signed", json_serialize_embed(value))
+            })
+            .map(|res: Result<_, _>| res.unwrap_or_else(|e| e));
+
+        let missing = warp::path("missing")
+            .map(|| CallResponse::new("missing").into_api_err(ApiErrorType::DataNotFound))
+            .map(|res: Result<_, _>| res.unwrap_or_else(|e| e));
+
+        let gateway = warp::path("gateway")
+            .map(|| warp::reply::with_status("Bad Gateway", StatusCode::BAD_GATEWAY));
+
+        let routes = address
+            .or(replay)
+            .or(request)
+            .or(missing)
+            .or(gateway)
+            .recover(handle_rejection);
+        let (addr, server): (SocketAddr, _) =
+            warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        format!("http://{addr}")
+    }
+
+    fn signed_client(base_url: &str, signing_mode: SigningMode) -> ValenceClient {
+        let (_, sk) = sign_ed25519::gen_keypair();
+        ValenceClient::new(base_url)
+            .unwrap()
+            .with_secret_key(sk)
+            .with_signing_mode(signing_mode)
+    }
+
+    #[tokio::test]
+    async fn should_sign_requests_for_each_middleware() {
+        //
+        // Arrange
+        //
+        let base_url = serve();
+
+        //
+        // Act
+        //
+        let address: Result<u32, _> = signed_client(&base_url, SigningMode::Address)
+            .get("/address")
+            .await;
+        let replay: Result<u32, _> = signed_client(&base_url, SigningMode::Replay)
+            .get("/replay")
+            .await;
+        let request: Result<Vec<u32>, _> = signed_client(&base_url, SigningMode::Request)
+            .post("/request?page=1", &vec![3, 4])
+            .await;
+
+        //
+        // Assert
+        //
+        assert_eq!(address, Ok(1));
+        assert_eq!(replay, Ok(2));
+        assert_eq!(request, Ok(vec![3, 4]));
+    }
+
+    #[tokio::test]
+    async fn should_parse_error_replies() {
+        //
+        // Arrange
+        //
+        let base_url = serve();
+        let unsigned = ValenceClient::new(&base_url).unwrap();
+
+        //
+        // Act
+        //
+        let missing: Result<u32, _> = unsigned.get("/missing").await;
+        let unsigned_call: Result<u32, _> = unsigned.get("/address").await;
+        let wrong_mode: Result<u32, _> = signed_client(&base_url, SigningMode::Address)
+            .get("/replay")
+            .await;
+        let gateway: Result<u32, _> = unsigned.get("/gateway").await;
+
+        //
+        // Assert
+        //
+        assert_eq!(missing, Err(ApiErrorType::DataNotFound));
+        assert_eq!(unsigned_call, Err(ApiErrorType::InvalidSignature));
+        assert_eq!(wrong_mode, Err(ApiErrorType::RequestExpired));
+        assert_eq!(
+            gateway,
+            Err(ApiErrorType::Generic(
+                "Node replied with 502 Bad Gateway".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn should_rebuild_error_payloads() {
+        //
+        // Arrange
+        //
+        let reply = br#"{"status":"Error","reason":"Missing request header: nonce","route":"","code":"MISSING_HEADER","content":"null"}"#;
+        let legacy =
+            br#"{"status":"Error","reason":"Something broke","route":"","content":"null"}"#;
+
+        //
+        // Act
+        //
+        let error = parse_reply::<u32>(reply);
+        let legacy_error = parse_reply::<u32>(legacy);
+
+        //
+        // Assert
+        //
+        assert_eq!(error, Err(ApiErrorType::MissingHeader("nonce".to_owned())));
+        assert_eq!(
+            legacy_error,
+            Err(ApiErrorType::Generic("Something broke".to_owned()))
+        );
+    }
+}
@@ -1,3 +1,10 @@
+pub mod address;
 pub mod api;
+pub mod canonical_json;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod crypto;
+pub mod merkle;
+pub mod multisig;
+pub mod signed;
 pub mod utils;
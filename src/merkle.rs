@@ -0,0 +1,275 @@
+use crate::crypto::sha3_256;
+use serde::{Deserialize, Serialize};
+
+pub const MERKLE_HASH_LEN: usize = 32;
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+pub type MerkleHash = [u8; MERKLE_HASH_LEN];
+
+/// Hashes a leaf, domain separated from internal nodes
+///
+/// ### Arguments
+///
+/// * `data` - Leaf data
+pub fn hash_leaf(data: &[u8]) -> MerkleHash {
+    sha3_256::digest_all([LEAF_PREFIX, data].into_iter()).into()
+}
+
+/// Hashes an internal node from its children, domain separated from leaves
+///
+/// ### Arguments
+///
+/// * `left` - Hash of the left child
+/// * `right` - Hash of the right child
+pub fn hash_node(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    sha3_256::digest_all([NODE_PREFIX, left.as_slice(), right.as_slice()].into_iter()).into()
+}
+
+/// Merkle tree over SHA3-256.
+/// A node without a sibling is promoted to the next level unchanged.
+/// Only built with `new`, so it is not deserializable; send `MerkleProof`s instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MerkleTree {
+    levels: Vec<Vec<MerkleHash>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree from the leaves' data. Returns `None` if there are no leaves.
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaves` - Data of each leaf, in order
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Option<Self> {
+        if leaves.is_empty() {
+            return None;
+        }
+
+        let mut levels = vec![leaves
+            .iter()
+            .map(|leaf| hash_leaf(leaf.as_ref()))
+            .collect::<Vec<_>>()];
+
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Some(Self { levels })
+    }
+
+    /// Root hash committing to every leaf
+    pub fn root(&self) -> MerkleHash {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Number of leaves in the tree
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds the inclusion proof for the leaf at `leaf_index`
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaf_index` - Index of the leaf to prove
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        if leaf_index >= self.len() {
+            return None;
+        }
+
+        let mut index = leaf_index;
+        let mut path = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling_index = index ^ 1;
+            if let Some(hash) = level.get(sibling_index) {
+                let side = if sibling_index < index {
+                    Side::Left
+                } else {
+                    Side::Right
+                };
+                path.push(ProofStep { hash: *hash, side });
+            }
+            index /= 2;
+        }
+
+        Some(MerkleProof {
+            leaf_index,
+            leaf_count: self.len(),
+            path,
+        })
+    }
+}
+
+/// Side on which a sibling hash is combined with the running hash
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A sibling hash on the path from a leaf to the root
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub hash: MerkleHash,
+    pub side: Side,
+}
+
+/// Inclusion proof of a single leaf
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    /// Number of leaves in the tree, which fixes the path shape for `leaf_index`
+    pub leaf_count: usize,
+    pub path: Vec<ProofStep>,
+}
+
+/// Sides of the proof steps for a leaf, skipping levels where it is promoted.
+/// Returns `None` if `leaf_index` is out of range.
+///
+/// ### Arguments
+///
+/// * `leaf_index` - Index of the leaf
+/// * `leaf_count` - Number of leaves in the tree
+fn path_sides(leaf_index: usize, leaf_count: usize) -> Option<Vec<Side>> {
+    if leaf_index >= leaf_count {
+        return None;
+    }
+
+    let (mut index, mut len) = (leaf_index, leaf_count);
+    let mut sides = Vec::new();
+    while len > 1 {
+        let sibling_index = index ^ 1;
+        if sibling_index < len {
+            sides.push(if sibling_index < index {
+                Side::Left
+            } else {
+                Side::Right
+            });
+        }
+        index /= 2;
+        len = len.div_ceil(2);
+    }
+
+    Some(sides)
+}
+
+impl MerkleProof {
+    /// Recomputes the root from the leaf data and the proof path
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaf` - Data of the proven leaf
+    pub fn compute_root(&self, leaf: &[u8]) -> MerkleHash {
+        self.path
+            .iter()
+            .fold(hash_leaf(leaf), |hash, step| match step.side {
+                Side::Left => hash_node(&step.hash, &hash),
+                Side::Right => hash_node(&hash, &step.hash),
+            })
+    }
+
+    /// Verifies that the leaf is included at `leaf_index` in the tree with the given root
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaf` - Data of the proven leaf
+    /// * `root` - Expected root hash
+    pub fn verify(&self, leaf: &[u8], root: &MerkleHash) -> bool {
+        let shape_matches = path_sides(self.leaf_index, self.leaf_count)
+            .is_some_and(|sides| sides.into_iter().eq(self.path.iter().map(|step| step.side)));
+
+        shape_matches && self.compute_root(leaf) == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_verify_proofs_for_every_leaf() {
+        //
+        // Arrange
+        //
+        let leaves: Vec<String> = (0..7).map(|i| format!("listing-{i}")).collect();
+        let tree = MerkleTree::new(&leaves).unwrap();
+
+        //
+        // Act
+        //
+        let proofs: Vec<MerkleProof> = (0..leaves.len()).map(|i| tree.proof(i).unwrap()).collect();
+
+        //
+        // Assert
+        //
+        for (leaf, proof) in leaves.iter().zip(&proofs) {
+            assert!(proof.verify(leaf.as_bytes(), &tree.root()));
+            assert!(!proof.verify(b"listing-x", &tree.root()));
+        }
+        assert!(tree.proof(leaves.len()).is_none());
+        assert!(MerkleTree::new::<&[u8]>(&[]).is_none());
+    }
+
+    #[test]
+    fn should_domain_separate_leaves_and_nodes() {
+        //
+        // Arrange
+        //
+        let left = hash_leaf(b"a");
+        let right = hash_leaf(b"b");
+        let node_preimage = [left, right].concat();
+
+        //
+        // Act
+        //
+        let tree = MerkleTree::new(&[b"a", b"b"]).unwrap();
+        let forged = MerkleTree::new(&[node_preimage]).unwrap();
+
+        //
+        // Assert
+        //
+        assert_eq!(tree.root(), hash_node(&left, &right));
+        assert_ne!(tree.root(), forged.root());
+    }
+
+    #[test]
+    fn should_reject_proof_with_wrong_leaf_index() {
+        //
+        // Arrange
+        //
+        let leaves: Vec<String> = (0..5).map(|i| format!("listing-{i}")).collect();
+        let tree = MerkleTree::new(&leaves).unwrap();
+        let proof = tree.proof(4).unwrap();
+
+        //
+        // Act
+        //
+        let moved = MerkleProof {
+            leaf_index: 2,
+            ..proof.clone()
+        };
+        let out_of_range = MerkleProof {
+            leaf_index: 5,
+            ..proof.clone()
+        };
+
+        //
+        // Assert
+        //
+        assert_eq!(proof.leaf_count, 5);
+        assert!(proof.verify(b"listing-4", &tree.root()));
+        assert!(!moved.verify(b"listing-4", &tree.root()));
+        assert!(!out_of_range.verify(b"listing-4", &tree.root()));
+    }
+}
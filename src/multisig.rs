@@ -0,0 +1,228 @@
+use crate::crypto::sign_ed25519::{self, PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// m-of-n policy: at least `threshold` of the `signers` must sign
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    pub threshold: usize,
+    pub signers: Vec<PublicKey>,
+}
+
+impl MultisigPolicy {
+    /// Creates a policy, checking that the threshold is reachable and signers are unique
+    ///
+    /// ### Arguments
+    ///
+    /// * `threshold` - Number of signatures required
+    /// * `signers` - Public keys allowed to sign
+    pub fn new(threshold: usize, signers: Vec<PublicKey>) -> Result<Self, MultisigError> {
+        let policy = Self { threshold, signers };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Checks that the threshold is reachable and signers are unique
+    pub fn validate(&self) -> Result<(), MultisigError> {
+        if self.threshold == 0 || self.threshold > self.signers.len() {
+            return Err(MultisigError::InvalidThreshold {
+                threshold: self.threshold,
+                signers: self.signers.len(),
+            });
+        }
+
+        let mut seen = BTreeSet::new();
+        match self.signers.iter().find(|pk| !seen.insert(**pk)) {
+            Some(pk) => Err(MultisigError::DuplicateSigner(*pk)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A signature together with the key that produced it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerSignature {
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+/// Signatures collected for a single message
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureBundle {
+    pub signatures: Vec<SignerSignature>,
+}
+
+impl SignatureBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signs the message and adds the signature to the bundle.
+    /// Returns the signer's public key, or `None` if the secret key is invalid.
+    ///
+    /// ### Arguments
+    ///
+    /// * `msg` - Message to sign
+    /// * `secret_key` - Secret key of the signer
+    pub fn sign(&mut self, msg: &[u8], secret_key: &SecretKey) -> Option<PublicKey> {
+        let public_key = secret_key.public_key()?;
+        self.signatures.push(SignerSignature {
+            public_key,
+            signature: sign_ed25519::sign_detached(msg, secret_key),
+        });
+        Some(public_key)
+    }
+}
+
+/// Multisignature verification error types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultisigError {
+    InvalidThreshold { threshold: usize, signers: usize },
+    DuplicateSigner(PublicKey),
+    UnknownSigner(PublicKey),
+    InvalidSignature(PublicKey),
+    ThresholdNotMet { threshold: usize, signed: usize },
+}
+
+impl std::fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MultisigError::InvalidThreshold { threshold, signers } => {
+                write!(f, "Invalid threshold {threshold} for {signers} signers")
+            }
+            MultisigError::DuplicateSigner(pk) => {
+                write!(f, "Duplicate signer: {}", hex::encode(pk))
+            }
+            MultisigError::UnknownSigner(pk) => {
+                write!(f, "Signer not in policy: {}", hex::encode(pk))
+            }
+            MultisigError::InvalidSignature(pk) => {
+                write!(f, "Invalid signature from: {}", hex::encode(pk))
+            }
+            MultisigError::ThresholdNotMet { threshold, signed } => write!(
+                f,
+                "Threshold not met: {signed} of {threshold} required signatures"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MultisigError {}
+
+/// Verifies a signature bundle against an m-of-n policy.
+/// Returns the keys that signed, in bundle order.
+///
+/// ### Arguments
+///
+/// * `policy` - Policy to verify against
+/// * `msg` - Message that was signed
+/// * `bundle` - Signatures over the message
+pub fn verify_multisig(
+    policy: &MultisigPolicy,
+    msg: &[u8],
+    bundle: &SignatureBundle,
+) -> Result<Vec<PublicKey>, MultisigError> {
+    policy.validate()?;
+
+    let mut signed = Vec::with_capacity(bundle.signatures.len());
+    for SignerSignature {
+        public_key,
+        signature,
+    } in &bundle.signatures
+    {
+        if signed.contains(public_key) {
+            return Err(MultisigError::DuplicateSigner(*public_key));
+        }
+        if !policy.signers.contains(public_key) {
+            return Err(MultisigError::UnknownSigner(*public_key));
+        }
+        if !sign_ed25519::verify_detached(signature, msg, public_key) {
+            return Err(MultisigError::InvalidSignature(*public_key));
+        }
+        signed.push(*public_key);
+    }
+
+    if signed.len() < policy.threshold {
+        return Err(MultisigError::ThresholdNotMet {
+            threshold: policy.threshold,
+            signed: signed.len(),
+        });
+    }
+
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypairs(count: usize) -> Vec<(PublicKey, SecretKey)> {
+        (0..count).map(|_| sign_ed25519::gen_keypair()).collect()
+    }
+
+    #[test]
+    fn should_verify_bundle_meeting_threshold() {
+        //
+        // Arrange
+        //
+        let keys = keypairs(3);
+        let policy = MultisigPolicy::new(2, keys.iter().map(|(pk, _)| *pk).collect()).unwrap();
+        let msg = b"Hello World!";
+        let mut bundle = SignatureBundle::new();
+        bundle.sign(msg, &keys[0].1).unwrap();
+
+        //
+        // Act
+        //
+        let below_threshold = verify_multisig(&policy, msg, &bundle);
+        bundle.sign(msg, &keys[2].1).unwrap();
+        let result = verify_multisig(&policy, msg, &bundle);
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            below_threshold,
+            Err(MultisigError::ThresholdNotMet {
+                threshold: 2,
+                signed: 1
+            })
+        );
+        assert_eq!(result, Ok(vec![keys[0].0, keys[2].0]));
+    }
+
+    #[test]
+    fn should_reject_duplicate_and_unknown_signers() {
+        //
+        // Arrange
+        //
+        let keys = keypairs(3);
+        let policy = MultisigPolicy::new(2, vec![keys[0].0, keys[1].0]).unwrap();
+        let msg = b"Hello World!";
+        let mut duplicate = SignatureBundle::new();
+        duplicate.sign(msg, &keys[0].1).unwrap();
+        duplicate.sign(msg, &keys[0].1).unwrap();
+        let mut unknown = SignatureBundle::new();
+        unknown.sign(msg, &keys[2].1).unwrap();
+
+        //
+        // Act
+        //
+        let duplicate_result = verify_multisig(&policy, msg, &duplicate);
+        let unknown_result = verify_multisig(&policy, msg, &unknown);
+        let duplicate_policy = MultisigPolicy::new(1, vec![keys[0].0, keys[0].0]);
+
+        //
+        // Assert
+        //
+        assert_eq!(
+            duplicate_result,
+            Err(MultisigError::DuplicateSigner(keys[0].0))
+        );
+        assert_eq!(unknown_result, Err(MultisigError::UnknownSigner(keys[2].0)));
+        assert_eq!(
+            duplicate_policy,
+            Err(MultisigError::DuplicateSigner(keys[0].0))
+        );
+    }
+}
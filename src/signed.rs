@@ -0,0 +1,178 @@
+use crate::canonical_json;
+use crate::crypto::{AnyPublicKey, AnySignature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Payload types that can be wrapped in a `Signed` envelope.
+/// The context string is signed along with the payload, so a signature over one
+/// payload type can't be replayed as another. It must be unique per type and must not contain NUL.
+pub trait Signable: Serialize {
+    const SIGNING_CONTEXT: &'static str;
+}
+
+/// Signed envelope error types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignedError {
+    InvalidSecretKey,
+    EncodingFailed,
+    InvalidSignature,
+}
+
+impl std::fmt::Display for SignedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SignedError::InvalidSecretKey => write!(f, "Invalid secret key"),
+            SignedError::EncodingFailed => write!(f, "Payload encoding failed"),
+            SignedError::InvalidSignature => write!(f, "Invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for SignedError {}
+
+/// A payload together with its signer's public key and signature, tagged with their algorithm
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signed<T> {
+    pub value: T,
+    pub public_key: AnyPublicKey,
+    pub signature: AnySignature,
+}
+
+impl<T: Signable> Signed<T> {
+    /// Signs the payload's canonical encoding under its signing context
+    ///
+    /// ### Arguments
+    ///
+    /// * `value` - Payload to sign
+    /// * `signer` - Secret key of any supported scheme to sign with
+    pub fn sign<S>(value: T, signer: &S) -> Result<Self, SignedError>
+    where
+        S: Signer,
+        S::PublicKey: Into<AnyPublicKey>,
+        S::Signature: Into<AnySignature>,
+    {
+        let public_key = signer.public_key().ok_or(SignedError::InvalidSecretKey)?;
+        let msg = signing_bytes(&value)?;
+        let signature = signer.sign(&msg);
+
+        Ok(Self {
+            value,
+            public_key: public_key.into(),
+            signature: signature.into(),
+        })
+    }
+
+    /// Verifies the signature and returns the payload if it's valid
+    pub fn verify(&self) -> Result<&T, SignedError> {
+        let msg = signing_bytes(&self.value)?;
+        if self.public_key.verify(&msg, &self.signature) {
+            Ok(&self.value)
+        } else {
+            Err(SignedError::InvalidSignature)
+        }
+    }
+
+    /// Verifies the signature and returns the payload, dropping the envelope
+    pub fn into_verified(self) -> Result<T, SignedError> {
+        self.verify()?;
+        Ok(self.value)
+    }
+}
+
+/// Bytes that get signed: the signing context, a NUL separator and the canonical payload
+///
+/// ### Arguments
+///
+/// * `value` - Payload to encode
+pub fn signing_bytes<T: Signable>(value: &T) -> Result<Vec<u8>, SignedError> {
+    let mut msg = T::SIGNING_CONTEXT.as_bytes().to_vec();
+    msg.push(0);
+    msg.extend(canonical_json::to_vec(value).map_err(|_| SignedError::EncodingFailed)?);
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sign_ed25519;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Listing {
+        title: String,
+        price: u64,
+    }
+
+    impl Signable for Listing {
+        const SIGNING_CONTEXT: &'static str = "valence_core/test/listing";
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Bid {
+        title: String,
+        price: u64,
+    }
+
+    impl Signable for Bid {
+        const SIGNING_CONTEXT: &'static str = "valence_core/test/bid";
+    }
+
+    #[test]
+    fn should_verify_signed_payload() {
+        //
+        // Arrange
+        //
+        let (_, sk) = sign_ed25519::gen_keypair();
+        let listing = Listing {
+            title: "Hello World!".to_string(),
+            price: 10,
+        };
+
+        //
+        // Act
+        //
+        let signed = Signed::sign(listing.clone(), &sk).unwrap();
+        let json = serde_json::to_string(&signed).unwrap();
+        let from_json: Signed<Listing> = serde_json::from_str(&json).unwrap();
+        let mut tampered = signed.clone();
+        tampered.value.price = 1;
+
+        //
+        // Assert
+        //
+        assert_eq!(signed.verify(), Ok(&listing));
+        assert_eq!(from_json.verify(), Ok(&listing));
+        assert_eq!(tampered.verify(), Err(SignedError::InvalidSignature));
+    }
+
+    #[test]
+    fn should_not_verify_signature_replayed_as_other_type() {
+        //
+        // Arrange
+        //
+        let (_, sk) = sign_ed25519::gen_keypair();
+        let signed = Signed::sign(
+            Listing {
+                title: "Hello World!".to_string(),
+                price: 10,
+            },
+            &sk,
+        )
+        .unwrap();
+
+        //
+        // Act
+        //
+        let replayed = Signed {
+            value: Bid {
+                title: signed.value.title.clone(),
+                price: signed.value.price,
+            },
+            public_key: signed.public_key,
+            signature: signed.signature,
+        };
+
+        //
+        // Assert
+        //
+        assert_eq!(replayed.verify(), Err(SignedError::InvalidSignature));
+    }
+}
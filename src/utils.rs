@@ -1,5 +1,6 @@
 use crate::crypto::sign_ed25519 as sign;
 use crate::crypto::sign_ed25519::{PublicKey, Signature};
+use crate::crypto::{AnyPublicKey, AnySignature, SignatureAlgorithm, Verifier};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
@@ -33,6 +34,50 @@ pub fn validate_signature(public_key: &str, msg: &str, signature: &str) -> bool
     )
 }
 
+/// Function to validate the signature using the given algorithm
+///
+/// ### Arguments
+///
+/// * `algorithm` - The signature algorithm used by the signer
+/// * `public_key` - The public key of the signer, hex encoded
+/// * `msg` - The message that was signed
+/// * `signature` - The signature of the message, hex encoded
+pub fn validate_signature_with_algorithm(
+    algorithm: SignatureAlgorithm,
+    public_key: &str,
+    msg: &str,
+    signature: &str,
+) -> bool {
+    let pk_decode = hex::decode(public_key).unwrap_or_default();
+    let sig_decode = hex::decode(signature).unwrap_or_default();
+
+    let pk = AnyPublicKey::from_slice(algorithm, &pk_decode);
+    let signature = AnySignature::from_slice(algorithm, &sig_decode);
+
+    match (pk, signature) {
+        (Some(pk), Some(signature)) => validate_any_signature(&pk, msg.as_bytes(), &signature),
+        _ => {
+            warn!("Failed to decode {algorithm} public key or signature");
+            false
+        }
+    }
+}
+
+/// Function to validate a signature of any supported scheme
+///
+/// ### Arguments
+///
+/// * `public_key` - The algorithm tagged public key of the signer
+/// * `msg` - The message that was signed
+/// * `signature` - The algorithm tagged signature of the message
+pub fn validate_any_signature(
+    public_key: &AnyPublicKey,
+    msg: &[u8],
+    signature: &AnySignature,
+) -> bool {
+    public_key.verify(msg, signature)
+}
+
 /// Function to serialize data
 pub fn serialize_data<T: Serialize>(data: &T) -> String {
     serde_json::to_string(data).unwrap_or_default()
@@ -92,4 +137,49 @@ mod tests {
         //
         assert!(!result);
     }
+
+    #[test]
+    fn should_validate_p256_signature_with_algorithm() {
+        //
+        // Arrange
+        //
+        use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let keypair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref()).unwrap();
+        let msg = "Hello World!";
+        let public_key = hex::encode(keypair.public_key());
+        let signature = hex::encode(keypair.sign(&rng, msg.as_bytes()).unwrap());
+
+        //
+        // Act
+        //
+        let result = validate_signature_with_algorithm(
+            SignatureAlgorithm::EcdsaP256,
+            &public_key,
+            msg,
+            &signature,
+        );
+        let as_ed25519 = validate_signature_with_algorithm(
+            SignatureAlgorithm::Ed25519,
+            &public_key,
+            msg,
+            &signature,
+        );
+        let wrong_msg = validate_signature_with_algorithm(
+            SignatureAlgorithm::EcdsaP256,
+            &public_key,
+            "Hello World?",
+            &signature,
+        );
+
+        //
+        // Assert
+        //
+        assert!(result);
+        assert!(!as_ed25519);
+        assert!(!wrong_msg);
+    }
 }